@@ -0,0 +1,306 @@
+//! Binary CCG format.
+//!
+//! All integers are little-endian. The file starts with a fixed 64 byte
+//! header
+//!
+//! ```text
+//! magic    [u8; 8]  "IASCARCG"
+//! version  u32
+//! flags    u32      reserved, 0
+//! nodes    u64
+//! edges    u64
+//! vars     u64      highest variable occurring in a leaf
+//! symbols  u64      number of atom mappings
+//! count    u64      byte length of the exact root count
+//! checksum u64      FNV-1a over everything following the header
+//! ```
+//!
+//! followed by the sections `kinds [u8; nodes]`, `offsets [u32; nodes + 1]`,
+//! `children [u32; edges]`, `lits [i32; nodes]`, `vals [i32; nodes]`,
+//! `var_offsets [u32; vars + 2]`, `var_leaves [u32; var_offsets[vars + 1]]`,
+//! the root count as unsigned base-256 digits (least significant first) and
+//! the symbol table as a sequence of `var i32, len u32, atom [u8; len]`.
+//! Every section starts at an 8 byte boundary, so the node arrays can be
+//! evaluated directly on the memory map.
+//!
+//! If bit 0 of `flags` is set, the symbol table is followed by literal
+//! weights as `n u64` and `n` entries `lit i32, len u32, weight [u8; len]`,
+//! where `weight` is a decimal fraction `p/q`.
+
+use rug::integer::Order;
+use rug::Integer;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::mmap::{Array, Mmap, Plain};
+use super::{parse_weight, Ccg, CcgError, Result, Weights, AND, LIT, OR};
+
+pub const MAGIC: &[u8; 8] = b"IASCARCG";
+pub const VERSION: u32 = 1;
+const HEADER_LEN: usize = 64;
+const WEIGHTED: u32 = 1;
+
+pub(super) fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |h, b| {
+        (h ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn padded(len: usize) -> usize {
+    (len + 7) & !7
+}
+
+fn header_field(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().expect("header field"))
+}
+
+fn truncated() -> CcgError {
+    CcgError::ReadingError("truncated binary ccg.".to_owned())
+}
+
+fn section<T: Plain>(map: &Arc<Mmap>, pos: &mut usize, len: usize) -> Result<Array<T>> {
+    let array = Array::view(map, *pos, len).ok_or_else(truncated)?;
+    // the view ends within the map, so neither end nor padding overflows
+    *pos = padded(*pos + len * T::SIZE);
+
+    Ok(array)
+}
+
+/// Checks the invariants the text reader establishes, such that evaluation
+/// cannot index out of bounds: children precede their parent within the
+/// child offsets, kinds are known, literals are within the leaf index and
+/// the leaf index refers to leaves over the respective variable.
+fn validate(
+    kinds: &[u8],
+    offsets: &[u32],
+    children: &[u32],
+    lits: &[i32],
+    var_offsets: &[u32],
+    var_leaves: &[u32],
+) -> Result<()> {
+    let invalid = |what: &str| Err(CcgError::ReadingError(format!("invalid {what}.")));
+    let var_count = var_offsets.len() - 2;
+
+    if offsets.first() != Some(&0) || offsets.last().map(|o| *o as usize) != Some(children.len()) {
+        return invalid("child offsets");
+    }
+    for node in 0..kinds.len() {
+        let (start, end) = (offsets[node] as usize, offsets[node + 1] as usize);
+        if start > end {
+            return invalid("child offsets");
+        }
+        if children[start..end].iter().any(|c| *c as usize >= node) {
+            return invalid(&format!("children of node {node}"));
+        }
+        match kinds[node] {
+            LIT if start == end && lits[node].unsigned_abs() as usize <= var_count => (),
+            AND | OR if lits[node] == 0 => (),
+            _ => return invalid(&format!("node {node}")),
+        }
+    }
+
+    if var_offsets.first() != Some(&0) || var_offsets.windows(2).any(|w| w[0] > w[1]) {
+        return invalid("leaf offsets");
+    }
+    for var in 0..=var_count {
+        let leaves = &var_leaves[var_offsets[var] as usize..var_offsets[var + 1] as usize];
+        let leaf_of_var = |leaf: &u32| {
+            let leaf = *leaf as usize;
+            leaf < kinds.len() && kinds[leaf] == LIT && lits[leaf].unsigned_abs() as usize == var
+        };
+        if !leaves.iter().all(leaf_of_var) {
+            return invalid(&format!("leaves of variable {var}"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks the sections of binary ccg `ccg` against the checksum of its
+/// header, which [`read`] skips to keep opening independent of file size.
+pub(super) fn verify(ccg: impl AsRef<Path>) -> Result<()> {
+    let map = Mmap::open(ccg)?;
+    let bytes = map.as_bytes();
+
+    if bytes.len() < HEADER_LEN || &bytes[..8] != MAGIC {
+        return Err(CcgError::ReadingError("not a binary ccg.".to_owned()));
+    }
+    match header_field(bytes, 56) == checksum(&bytes[HEADER_LEN..]) {
+        true => Ok(()),
+        _ => Err(CcgError::ReadingError("checksum mismatch.".to_owned())),
+    }
+}
+
+pub(super) fn read(ccg: impl AsRef<Path>) -> Result<Ccg> {
+    let map = Arc::new(Mmap::open(ccg)?);
+    let bytes = map.as_bytes();
+
+    if bytes.len() < HEADER_LEN || &bytes[..8] != MAGIC {
+        return Err(CcgError::ReadingError("not a binary ccg.".to_owned()));
+    }
+    let version = u32::from_le_bytes(bytes[8..12].try_into().expect("version"));
+    let flags = u32::from_le_bytes(bytes[12..16].try_into().expect("flags"));
+    if version != VERSION {
+        return Err(CcgError::ReadingError(format!(
+            "unsupported binary ccg version {version}."
+        )));
+    }
+
+    let node_count = header_field(bytes, 16) as usize;
+    let edge_count = header_field(bytes, 24) as usize;
+    let var_count = header_field(bytes, 32) as usize;
+    let symbol_count = header_field(bytes, 40) as usize;
+    let count_len = header_field(bytes, 48) as usize;
+
+    let mut pos = HEADER_LEN;
+    let kinds = section(&map, &mut pos, node_count)?;
+    let offsets = section::<u32>(
+        &map,
+        &mut pos,
+        node_count.checked_add(1).ok_or_else(truncated)?,
+    )?;
+    let children = section(&map, &mut pos, edge_count)?;
+    let lits = section(&map, &mut pos, node_count)?;
+    let vals = section(&map, &mut pos, node_count)?;
+    let var_offsets = section::<u32>(
+        &map,
+        &mut pos,
+        var_count.checked_add(2).ok_or_else(truncated)?,
+    )?;
+    let var_leaves = section(&map, &mut pos, var_offsets[var_count + 1] as usize)?;
+    validate(
+        &kinds,
+        &offsets,
+        &children,
+        &lits,
+        &var_offsets,
+        &var_leaves,
+    )?;
+
+    let end = pos.checked_add(count_len).ok_or_else(truncated)?;
+    let count = bytes
+        .get(pos..end)
+        .map(|digits| Integer::from_digits(digits, Order::Lsf))
+        .ok_or(CcgError::ReadingError("truncated root count.".to_owned()))?;
+    pos = padded(end);
+
+    let mut mapping = HashMap::with_capacity(symbol_count);
+    for _ in 0..symbol_count {
+        let (var, atom) = entry(bytes, &mut pos)
+            .ok_or(CcgError::ReadingError("truncated symbol table.".to_owned()))?;
+        mapping.insert(atom.to_owned(), var);
+    }
+
+    let mut weights = Weights::default();
+    if flags & WEIGHTED != 0 {
+        let n = bytes
+            .get(pos..pos + 8)
+            .map(|b| u64::from_le_bytes(b.try_into().expect("weight count")))
+            .ok_or(CcgError::ReadingError("truncated weights.".to_owned()))?;
+        pos += 8;
+        for _ in 0..n {
+            let (lit, weight) = entry(bytes, &mut pos)
+                .ok_or(CcgError::ReadingError("truncated weights.".to_owned()))?;
+            let weight = parse_weight(weight).ok_or(CcgError::ReadingError(format!(
+                "could not read weight of literal {lit}."
+            )))?;
+            weights.insert(lit, weight);
+        }
+    }
+
+    Ok(Ccg {
+        kinds,
+        offsets,
+        children,
+        lits,
+        vals,
+        var_offsets,
+        var_leaves,
+        mapping,
+        weights,
+        edge_count,
+        log10_count: count.to_f64().log10(),
+    })
+}
+
+/// Reads an entry `int i32, len u32, [u8; len]` of the symbol table or the
+/// weights.
+fn entry<'a>(bytes: &'a [u8], pos: &mut usize) -> Option<(i32, &'a str)> {
+    let b = bytes.get(*pos..*pos + 8)?;
+    let (int, len) = (
+        <i32 as Plain>::from_le(&b[..4]),
+        <u32 as Plain>::from_le(&b[4..]) as usize,
+    );
+    let s = bytes
+        .get(*pos + 8..*pos + 8 + len)
+        .and_then(|b| std::str::from_utf8(b).ok())?;
+    *pos += 8 + len;
+
+    Some((int, s))
+}
+
+fn push_entry(buf: &mut Vec<u8>, int: i32, s: &str) {
+    buf.extend(int.to_le_bytes());
+    buf.extend((s.len() as u32).to_le_bytes());
+    buf.extend(s.as_bytes());
+}
+
+fn push<T: Plain>(buf: &mut Vec<u8>, xs: &[T]) {
+    xs.iter().for_each(|x| x.to_le(buf));
+    buf.resize(padded(buf.len()), 0);
+}
+
+pub(super) fn write(graph: &Ccg, out: &mut impl Write) -> Result<()> {
+    let count = graph.count(&[]).to_digits::<u8>(Order::Lsf);
+
+    let mut buf = vec![0u8; HEADER_LEN];
+    push(&mut buf, &graph.kinds);
+    push(&mut buf, &graph.offsets);
+    push(&mut buf, &graph.children);
+    push(&mut buf, &graph.lits);
+    push(&mut buf, &graph.vals);
+    push(&mut buf, &graph.var_offsets);
+    push(&mut buf, &graph.var_leaves);
+    push(&mut buf, &count);
+
+    let mut symbols = graph.mapping.iter().collect::<Vec<_>>();
+    symbols.sort_by_key(|(_, v)| **v);
+    for (atom, var) in &symbols {
+        push_entry(&mut buf, **var, atom);
+    }
+
+    let flags = match graph.weights.is_empty() {
+        true => 0,
+        _ => {
+            buf.extend((graph.weights.len() as u64).to_le_bytes());
+            for (lit, weight) in graph.weights.iter() {
+                push_entry(&mut buf, lit, &weight.to_string());
+            }
+            WEIGHTED
+        }
+    };
+
+    buf[..8].copy_from_slice(MAGIC);
+    buf[8..12].copy_from_slice(&VERSION.to_le_bytes());
+    buf[12..16].copy_from_slice(&flags.to_le_bytes());
+    for (at, field) in [
+        graph.node_count(),
+        graph.edge_count(),
+        graph.var_count(),
+        symbols.len(),
+        count.len(),
+    ]
+    .iter()
+    .enumerate()
+    {
+        buf[16 + 8 * at..24 + 8 * at].copy_from_slice(&(*field as u64).to_le_bytes());
+    }
+    let sum = checksum(&buf[HEADER_LEN..]);
+    buf[56..64].copy_from_slice(&sum.to_le_bytes());
+
+    out.write_all(&buf)?;
+
+    Ok(())
+}
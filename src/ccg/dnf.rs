@@ -0,0 +1,215 @@
+use itertools::Itertools;
+use rug::Integer;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use super::{Ccg, CcgError, Result};
+
+#[allow(unused)]
+impl Ccg {
+    /// Returns the number of models under `assumptions` satisfying at least
+    /// one of `terms`, each a conjunction of literals.
+    ///
+    /// Splits on the variables of the terms, i.e., counts models with `v` and
+    /// with `~v` separately, such that every model is counted once. Branches
+    /// without models under the assumptions so far are cut, so the number of
+    /// counts is exponential only in the variables that remain relevant.
+    pub fn count_dnf(&self, terms: &[Vec<i32>], assumptions: &[i32]) -> Integer {
+        let mut assumptions = assumptions.to_vec();
+        self.split(terms.to_vec(), &mut assumptions)
+    }
+
+    fn split(&self, terms: Vec<Vec<i32>>, assumptions: &mut Vec<i32>) -> Integer {
+        if terms.is_empty() {
+            return Integer::new();
+        }
+        let count = self.count(assumptions);
+        if count == 0 || terms.iter().any(|t| t.is_empty()) {
+            return count;
+        }
+
+        let var = terms[0][0].abs();
+        let mut total = Integer::new();
+        for lit in [var, -var] {
+            let reduced = terms
+                .iter()
+                .filter(|t| !t.contains(&-lit))
+                .map(|t| t.iter().copied().filter(|l| *l != lit).collect())
+                .collect();
+            assumptions.push(lit);
+            total += self.split(reduced, assumptions);
+            assumptions.pop();
+        }
+
+        total
+    }
+
+    /// Returns the number of models under `assumptions` that satisfy none of
+    /// `denied`, i.e., under integrity constraints with bodies `denied`.
+    pub fn count_denying(&self, denied: &[Vec<i32>], assumptions: &[i32]) -> Integer {
+        self.count(assumptions) - self.count_dnf(denied, assumptions)
+    }
+
+    /// Like [`Ccg::count_denying`], but by inclusion–exclusion over the
+    /// denied terms, stopping after `depth` alternations (all for 0).
+    ///
+    /// Stopping after an odd (even) number of alternations yields a lower
+    /// (upper) bound. The result is exact if every intersection at the last
+    /// alternation has no model, as then neither has any larger one.
+    pub fn count_denying_to_depth(
+        &self,
+        denied: &[Vec<i32>],
+        assumptions: &[i32],
+        depth: usize,
+    ) -> Integer {
+        self.count_denying_anytime(denied, assumptions, depth, |_, _| true)
+    }
+
+    /// Like [`Ccg::count_denying_to_depth`], but passes the bound after every
+    /// alternation, starting with the count without `denied` at 0, to
+    /// `bound`, which stops the evaluation by returning `false`.
+    pub fn count_denying_anytime(
+        &self,
+        denied: &[Vec<i32>],
+        assumptions: &[i32],
+        depth: usize,
+        mut bound: impl FnMut(usize, &Integer) -> bool,
+    ) -> Integer {
+        let n = denied.len();
+        let depth = if depth == 0 || depth > n { n } else { depth };
+        let mut vals = vec![];
+        let mut count = self.count_with(assumptions, &mut vals);
+        if !bound(0, &count) {
+            return count;
+        }
+
+        for i in 1..=depth {
+            let mut level = Integer::new();
+            for gamma in (0..n).combinations(i) {
+                let mut assumptions = assumptions.to_vec();
+                gamma.iter().for_each(|j| assumptions.extend(&denied[*j]));
+                level += self.count_with(&assumptions, &mut vals);
+            }
+            if level == 0 {
+                break;
+            }
+            match i % 2 {
+                1 => count -= level,
+                _ => count += level,
+            }
+            if !bound(i, &count) {
+                break;
+            }
+        }
+
+        count
+    }
+}
+
+/// Parses ground integrity constraints `:- l1, ..., ln.` into their bodies,
+/// where each `li` is an atom `a`, its negation `not a` or `~a`, or a cnf
+/// literal. Lines are cut at `%`.
+pub fn parse_constraints(s: &str, mapping: &HashMap<String, i32>) -> Result<Vec<Vec<i32>>> {
+    let literal = |l: &str| {
+        let l = l.trim();
+        i32::from_str(l).ok().or_else(|| {
+            match l.strip_prefix("not ").or_else(|| l.strip_prefix('~')) {
+                Some(a) => mapping.get(a.trim()).map(|i| -i),
+                _ => mapping.get(l).copied(),
+            }
+        })
+    };
+
+    // drop comments
+    let s = s
+        .lines()
+        .map(|l| l.split('%').next().unwrap_or_default())
+        .join("\n");
+
+    let mut constraints = vec![];
+    for constraint in split_top_level(&s, '.') {
+        let constraint = constraint.trim();
+        if constraint.is_empty() {
+            continue;
+        }
+        let body = constraint
+            .strip_prefix(":-")
+            .ok_or(CcgError::ReadingError(format!(
+                "could not read constraint {constraint:?}."
+            )))?;
+        let mut denied = vec![];
+        for l in split_top_level(body, ',').filter(|l| !l.trim().is_empty()) {
+            denied.push(literal(l).ok_or(CcgError::ReadingError(format!(
+                "could not read literal {:?}.",
+                l.trim()
+            )))?);
+        }
+        constraints.push(denied);
+    }
+
+    Ok(constraints)
+}
+
+/// Splits `s` at occurrences of `sep` outside of parentheses.
+fn split_top_level(s: &str, sep: char) -> impl Iterator<Item = &str> {
+    let mut depth = 0;
+    s.split(move |c| {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => (),
+        }
+        c == sep && depth == 0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ccg::Result;
+
+    #[test]
+    fn count_dnf_by_inclusion_exclusion() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.as.cnf.nnf.ccg")?;
+        // a | b | (c & ~d)
+        let terms = [vec![10], vec![9], vec![15, -14]];
+        let expected = graph.count(&[10]) + graph.count(&[9]) + graph.count(&[15, -14])
+            - graph.count(&[10, 9])
+            - graph.count(&[10, 15, -14])
+            - graph.count(&[9, 15, -14])
+            + graph.count(&[10, 9, 15, -14]);
+        assert_eq!(graph.count_dnf(&terms, &[]), expected);
+        assert_eq!(graph.count_dnf(&terms, &[-10]), graph.count(&[9, -10]));
+
+        assert_eq!(graph.count_dnf(&[], &[]), 0);
+        assert_eq!(graph.count_dnf(&[vec![]], &[]), graph.count(&[]));
+        Ok(())
+    }
+
+    #[test]
+    fn count_under_denials() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.as.cnf.nnf.ccg")?;
+        let denied = parse_constraints(
+            ":- a, not e. % a implies e\n:- b, ~c. :- h.",
+            graph.mapping(),
+        )?;
+        assert_eq!(denied, vec![vec![10, -19], vec![9, -15], vec![4]]);
+
+        let exact = graph.count_denying(&denied, &[]);
+        assert_eq!(exact, graph.count(&[-4]) - graph.count(&[-4, 9, -15]));
+        assert_eq!(graph.count_denying_to_depth(&denied, &[], 0), exact);
+        assert!(graph.count_denying_to_depth(&denied, &[], 1) <= exact);
+        let mut bounds = vec![];
+        let first = graph.count_denying_anytime(&denied, &[], 0, |i, bound| {
+            bounds.push((i, bound.clone()));
+            i < 1
+        });
+        assert_eq!(first, graph.count_denying_to_depth(&denied, &[], 1));
+        assert_eq!(bounds, vec![(0, graph.count(&[])), (1, first)]);
+        assert_eq!(graph.count_denying(&denied, &[4]), 0);
+
+        assert!(parse_constraints("a :- b.", graph.mapping()).is_err());
+        assert!(parse_constraints(":- x.", graph.mapping()).is_err());
+        Ok(())
+    }
+}
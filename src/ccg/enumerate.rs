@@ -0,0 +1,261 @@
+use rug::Integer;
+
+use super::{Ccg, AND, LIT, OR};
+
+/// Lazy iterator over the models of a [`Ccg`] under assumptions.
+///
+/// A model is determined by the child chosen at every or-node reached from
+/// the root. Models are enumerated in the canonical order of these choices,
+/// taken in depth-first order, where the last choice changes fastest. Each
+/// step backtracks to the last choice with a remaining child of nonzero
+/// count and completes it depth-first, so the delay between two models is
+/// linear in the size of the graph.
+#[allow(unused)]
+#[derive(Debug, Clone)]
+pub struct Models<'a> {
+    graph: &'a Ccg,
+    alive: Vec<bool>,
+    /// Or-nodes in depth-first order along with the position of the chosen
+    /// child.
+    choices: Vec<(u32, u32)>,
+    started: bool,
+}
+
+#[allow(unused)]
+impl<'a> Models<'a> {
+    pub fn new(graph: &'a Ccg, assumptions: &[i32]) -> Self {
+        let mut vals = vec![];
+        graph.count_with(assumptions, &mut vals);
+
+        Self {
+            graph,
+            alive: vals.iter().map(|v| *v > 0).collect(),
+            choices: vec![],
+            started: false,
+        }
+    }
+
+    /// Starts the enumeration at the model with (0-based) `index`, computing
+    /// its choices top-down from the node counts.
+    ///
+    /// An and-node reads `index` in the mixed radix given by the counts of
+    /// its children, with the first child as most significant digit. An
+    /// or-node passes on `index` less the counts of the preceding children to
+    /// the child it falls into.
+    pub fn starting_at(graph: &'a Ccg, assumptions: &[i32], index: &Integer) -> Self {
+        let mut vals = vec![];
+        graph.count_with(assumptions, &mut vals);
+        let mut models = Self {
+            graph,
+            alive: vals.iter().map(|v| *v > 0).collect(),
+            choices: vec![],
+            started: false,
+        };
+        if vals
+            .last()
+            .is_none_or(|count| *index < 0 || *index >= *count)
+        {
+            models.started = true;
+            return models;
+        }
+
+        let mut stack = vec![(graph.root(), index.clone())];
+        while let Some((node, mut index)) = stack.pop() {
+            match graph.kinds[node] {
+                LIT => (),
+                AND => {
+                    // push last to first child, such that the first is popped first
+                    for child in graph.children(node).iter().rev() {
+                        let (quotient, digit) = index.div_rem(vals[*child as usize].clone());
+                        stack.push((*child as usize, digit));
+                        index = quotient;
+                    }
+                }
+                _ => {
+                    for (pos, child) in graph.children(node).iter().enumerate() {
+                        let count = &vals[*child as usize];
+                        if index < *count {
+                            models.choices.push((node as u32, pos as u32));
+                            stack.push((*child as usize, index));
+                            break;
+                        }
+                        index -= count;
+                    }
+                }
+            }
+        }
+
+        models
+    }
+
+    /// Moves the last choice with a remaining alternative to its next child
+    /// and drops all subsequent choices.
+    fn advance(&mut self) -> bool {
+        while let Some((node, pos)) = self.choices.pop() {
+            let children = self.graph.children(node as usize);
+            if let Some(next) =
+                (pos as usize + 1..children.len()).find(|i| self.alive[children[*i] as usize])
+            {
+                self.choices.push((node, next as u32));
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Collects the literals of the model given by the current choices,
+    /// completing them with the first alive child of every further or-node.
+    fn complete(&mut self) -> Vec<i32> {
+        let graph = self.graph;
+        let mut model = vec![];
+        let mut next_choice = 0;
+        let mut stack = vec![graph.root()];
+
+        while let Some(node) = stack.pop() {
+            match graph.kinds[node] {
+                LIT => model.push(graph.lits[node]),
+                AND => stack.extend(graph.children(node).iter().rev().map(|c| *c as usize)),
+                _ => {
+                    let children = graph.children(node);
+                    let pos = match self.choices.get(next_choice) {
+                        Some((_, pos)) => *pos as usize,
+                        _ => {
+                            let pos = children
+                                .iter()
+                                .position(|c| self.alive[*c as usize])
+                                .expect("alive or-node has an alive child");
+                            self.choices.push((node as u32, pos as u32));
+                            pos
+                        }
+                    };
+                    next_choice += 1;
+                    stack.push(children[pos] as usize);
+                }
+            }
+        }
+        model.sort_by_key(|l| l.abs());
+
+        model
+    }
+}
+
+impl Iterator for Models<'_> {
+    type Item = Vec<i32>;
+
+    fn next(&mut self) -> Option<Vec<i32>> {
+        if self.graph.node_count() == 0 {
+            return None;
+        }
+        let more = match self.started {
+            true => self.advance(),
+            _ => self.alive[self.graph.root()],
+        };
+        self.started = true;
+
+        match more {
+            true => Some(self.complete()),
+            _ => None,
+        }
+    }
+}
+
+#[allow(unused)]
+impl Ccg {
+    /// Returns a lazy iterator over the models under `assumptions`, each as
+    /// literals ordered by variable.
+    pub fn models(&self, assumptions: &[i32]) -> Models<'_> {
+        Models::new(self, assumptions)
+    }
+
+    /// Returns the model with (0-based) `index` in the order of
+    /// [`Ccg::models`] under `assumptions`.
+    pub fn model_at(&self, index: &Integer, assumptions: &[i32]) -> Option<Vec<i32>> {
+        Models::starting_at(self, assumptions, index).next()
+    }
+
+    /// Returns the index of `model` in the order of [`Ccg::models`] under
+    /// `assumptions`, or `None` if `model` does not determine exactly one
+    /// model under `assumptions`.
+    pub fn rank(&self, model: &[i32], assumptions: &[i32]) -> Option<Integer> {
+        let (mut vals, mut within) = (vec![], vec![]);
+        self.count_with(assumptions, &mut vals);
+        let mut both = assumptions.to_vec();
+        both.extend(model);
+        if self.count_with(&both, &mut within) != 1 {
+            return None;
+        }
+
+        let mut ranks = vec![Integer::new(); self.node_count()];
+        for node in (0..self.node_count()).filter(|n| within[*n] > 0) {
+            let children = self.children(node);
+            match self.kinds[node] {
+                LIT => (),
+                OR => {
+                    let mut rank = Integer::new();
+                    for child in children {
+                        let child = *child as usize;
+                        match within[child] > 0 {
+                            true => {
+                                rank += &ranks[child];
+                                break;
+                            }
+                            _ => rank += &vals[child],
+                        }
+                    }
+                    ranks[node] = rank;
+                }
+                _ => {
+                    let mut rank = Integer::new();
+                    for child in children {
+                        rank *= &vals[*child as usize];
+                        rank += &ranks[*child as usize];
+                    }
+                    ranks[node] = rank;
+                }
+            }
+        }
+
+        ranks.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ccg::Result;
+    use std::collections::HashSet;
+
+    #[test]
+    fn enumerates_every_model_once() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.sm.cnf.nnf.ccg")?;
+        for assumptions in [vec![], vec![-10], vec![5, -3], vec![10, -10]] {
+            let models = graph.models(&assumptions).collect::<Vec<_>>();
+            let distinct = models.iter().collect::<HashSet<_>>();
+            assert_eq!(Integer::from(distinct.len()), graph.count(&assumptions));
+            assert_eq!(distinct.len(), models.len());
+            for model in &models {
+                assert_eq!(graph.count(model), 1);
+                assert!(assumptions.iter().all(|a| model.contains(a)));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rank_inverts_model_at() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.sm.cnf.nnf.ccg")?;
+        for assumptions in [vec![], vec![-10], vec![5, -3]] {
+            let count = graph.count(&assumptions);
+            for (i, model) in graph.models(&assumptions).enumerate() {
+                let index = Integer::from(i);
+                assert_eq!(graph.model_at(&index, &assumptions).as_ref(), Some(&model));
+                assert_eq!(graph.rank(&model, &assumptions), Some(index));
+            }
+            assert_eq!(graph.model_at(&count, &assumptions), None);
+            let rest = Models::starting_at(&graph, &assumptions, &Integer::from(2)).count();
+            assert_eq!(Integer::from(rest + 2), count);
+        }
+        Ok(())
+    }
+}
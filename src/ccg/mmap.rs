@@ -0,0 +1,162 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result};
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Read-only memory map of a whole file.
+///
+/// Falls back to reading the file into an 8-byte aligned heap buffer on
+/// platforms without `mmap`.
+pub struct Mmap {
+    #[cfg(unix)]
+    ptr: *mut libc::c_void,
+    #[cfg(not(unix))]
+    buf: Vec<u64>,
+    len: usize,
+}
+
+// SAFETY: the mapping is read-only and owned by `Mmap`.
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    #[cfg(unix)]
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        use std::os::unix::io::AsRawFd;
+
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        if len == 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "empty file"));
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(Error::last_os_error());
+        }
+
+        Ok(Self { ptr, len })
+    }
+
+    #[cfg(not(unix))]
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        use std::io::Read;
+
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        if len == 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "empty file"));
+        }
+
+        let mut buf = vec![0u64; (len + 7) / 8];
+        let bytes = unsafe { std::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, len) };
+        file.read_exact(bytes)?;
+
+        Ok(Self { buf, len })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        #[cfg(unix)]
+        let ptr = self.ptr as *const u8;
+        #[cfg(not(unix))]
+        let ptr = self.buf.as_ptr() as *const u8;
+
+        unsafe { std::slice::from_raw_parts(ptr, self.len) }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr, self.len);
+        }
+    }
+}
+
+/// Plain little-endian integer types that can be viewed in place.
+pub trait Plain: Copy + Default + 'static {
+    const SIZE: usize;
+    fn from_le(bytes: &[u8]) -> Self;
+    fn to_le(&self, buf: &mut Vec<u8>);
+}
+
+macro_rules! plain {
+    ($($t:ty),*) => {$(
+        impl Plain for $t {
+            const SIZE: usize = std::mem::size_of::<$t>();
+            fn from_le(bytes: &[u8]) -> Self {
+                <$t>::from_le_bytes(bytes.try_into().expect("slice of size SIZE"))
+            }
+            fn to_le(&self, buf: &mut Vec<u8>) {
+                buf.extend(self.to_le_bytes());
+            }
+        }
+    )*};
+}
+plain!(u8, u32, i32);
+
+/// Flat array that is either owned or borrowed from a memory map.
+#[derive(Clone)]
+pub enum Array<T: Plain> {
+    Owned(Vec<T>),
+    Mapped {
+        map: Arc<Mmap>,
+        offset: usize,
+        len: usize,
+    },
+}
+
+impl<T: Plain> Array<T> {
+    /// Views `len` elements at byte `offset` of `map`, copying only if the
+    /// data is misaligned or the host is big-endian. Returns `None` if they
+    /// exceed the map.
+    pub fn view(map: &Arc<Mmap>, offset: usize, len: usize) -> Option<Self> {
+        let end = len.checked_mul(T::SIZE)?.checked_add(offset)?;
+        let bytes = map.as_bytes().get(offset..end)?;
+        let aligned = bytes.as_ptr().align_offset(std::mem::align_of::<T>()) == 0;
+        Some(match cfg!(target_endian = "little") && aligned {
+            true => Self::Mapped {
+                map: map.clone(),
+                offset,
+                len,
+            },
+            _ => Self::Owned(bytes.chunks_exact(T::SIZE).map(T::from_le).collect()),
+        })
+    }
+}
+
+impl<T: Plain> Deref for Array<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self {
+            Self::Owned(v) => v,
+            Self::Mapped { map, offset, len } => unsafe {
+                std::slice::from_raw_parts(map.as_bytes().as_ptr().add(*offset) as *const T, *len)
+            },
+        }
+    }
+}
+
+impl<T: Plain> From<Vec<T>> for Array<T> {
+    fn from(v: Vec<T>) -> Self {
+        Self::Owned(v)
+    }
+}
+
+impl<T: Plain + std::fmt::Debug> std::fmt::Debug for Array<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self[..].fmt(f)
+    }
+}
@@ -0,0 +1,728 @@
+mod binary;
+mod dnf;
+mod enumerate;
+mod mmap;
+mod optimal;
+mod sample;
+mod semiring;
+mod transform;
+mod weighted;
+
+use rug::{Assign, Integer};
+use std::collections::HashMap;
+use std::fs::{read_to_string, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::{SAND, SOR};
+use mmap::Array;
+#[allow(unused)]
+pub use semiring::{
+    Boolean, Counting, FloatSumProduct, GapCounting, Gradient, MaxProduct, MinSum, Selective,
+    Semiring, SumProduct,
+};
+#[allow(unused)]
+pub use enumerate::Models;
+pub use sample::Sampler;
+pub use dnf::parse_constraints;
+pub use weighted::{parse_weight, Weights};
+
+pub const LIT: u8 = 2;
+pub const AND: u8 = 1;
+pub const OR: u8 = 0;
+
+#[derive(Error, Debug)]
+pub enum CcgError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("reading data failed: {0}")]
+    ReadingError(String),
+}
+
+pub type Result<T> = std::result::Result<T, CcgError>;
+
+/// Compressed counting graph loaded once into flat arrays.
+///
+/// Nodes are stored in topological order (children before parents, root
+/// last). Children of node `i` are `children[offsets[i]..offsets[i + 1]]`;
+/// literal leaves have no children and carry their literal and value in
+/// `lits` and `vals`. Leaves are additionally indexed by variable.
+///
+/// Graphs read from the binary format keep their arrays on the memory map.
+/// Literal weights recorded alongside the graph, e.g. probabilities of
+/// probabilistic facts, are kept in `weights`.
+#[allow(unused)]
+#[derive(Debug, Clone)]
+pub struct Ccg {
+    kinds: Array<u8>,
+    offsets: Array<u32>,
+    children: Array<u32>,
+    lits: Array<i32>,
+    vals: Array<i32>,
+    var_offsets: Array<u32>,
+    var_leaves: Array<u32>,
+    mapping: HashMap<String, i32>,
+    weights: Weights,
+    edge_count: usize,
+    log10_count: f64,
+}
+
+#[allow(unused)]
+impl Ccg {
+    /// Reads a ccg in either textual or binary format.
+    pub fn read(ccg: impl AsRef<Path>) -> Result<Self> {
+        match Self::is_binary(&ccg)? {
+            true => binary::read(ccg),
+            _ => read_to_string(ccg)?.parse(),
+        }
+    }
+
+    /// Returns whether `ccg` starts with the magic of the binary format.
+    pub fn is_binary(ccg: impl AsRef<Path>) -> Result<bool> {
+        let mut magic = [0u8; 8];
+        let read = File::open(ccg)?.read_exact(&mut magic);
+
+        Ok(read.is_ok() && &magic == binary::MAGIC)
+    }
+
+    /// Checks a binary ccg against its checksum, which [`Ccg::read`] does
+    /// not. Textual ccgs have no checksum and always pass.
+    pub fn verify(ccg: impl AsRef<Path>) -> Result<()> {
+        match Self::is_binary(&ccg)? {
+            true => binary::verify(ccg),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes graph in the binary format.
+    pub fn write_binary(&self, out: &mut impl Write) -> Result<()> {
+        binary::write(self, out)
+    }
+
+    /// Writes graph in the textual format produced by the compressor.
+    pub fn write_text(&self, out: &mut impl Write) -> Result<()> {
+        writeln!(
+            out,
+            "ccg {:?} {:?} {:?} {:?}",
+            self.node_count(),
+            self.edge_count(),
+            self.lits.iter().filter(|l| **l > 0).count(),
+            self.log10_count
+        )?;
+
+        let mut symbols = self.mapping.iter().collect::<Vec<_>>();
+        symbols.sort_by_key(|(_, v)| **v);
+        for (atom, int) in symbols {
+            writeln!(out, "c {:?} {}", int, atom)?;
+        }
+        for (lit, weight) in self.weights.iter() {
+            writeln!(out, "c p weight {:?} {} 0", lit, weight)?;
+        }
+
+        for node in 0..self.node_count() {
+            match self.kinds[node] {
+                LIT => writeln!(out, "{:?} {:?} ", self.lits[node], self.vals[node])?,
+                kind => {
+                    let children = self.children(node);
+                    write!(
+                        out,
+                        "{} {:?}",
+                        if kind == AND { SAND } else { SOR },
+                        children.len()
+                    )?;
+                    for child in children {
+                        write!(out, " {:?}", child)?;
+                    }
+                    writeln!(out)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.kinds.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edge_count
+    }
+
+    /// Returns the highest variable occurring in a literal leaf.
+    pub fn var_count(&self) -> usize {
+        self.var_offsets.len() - 2
+    }
+
+    /// Returns log10 of the overall count as stated in the header.
+    pub fn log10_count(&self) -> f64 {
+        self.log10_count
+    }
+
+    pub fn mapping(&self) -> &HashMap<String, i32> {
+        &self.mapping
+    }
+
+    /// Returns literal weights recorded in the graph.
+    pub fn weights(&self) -> &Weights {
+        &self.weights
+    }
+
+    pub fn root(&self) -> usize {
+        self.node_count() - 1
+    }
+
+    pub fn kind(&self, node: usize) -> u8 {
+        self.kinds[node]
+    }
+
+    pub fn children(&self, node: usize) -> &[u32] {
+        &self.children[self.offsets[node] as usize..self.offsets[node + 1] as usize]
+    }
+
+    /// Returns literal of leaf `node`, `0` for gates.
+    pub fn literal(&self, node: usize) -> i32 {
+        self.lits[node]
+    }
+
+    /// Returns value of leaf `node`, `0` for gates.
+    pub fn value(&self, node: usize) -> i32 {
+        self.vals[node]
+    }
+
+    /// Returns the literal leaves over variable `var`.
+    pub fn leaves_of(&self, var: usize) -> &[u32] {
+        match var <= self.var_count() {
+            true => {
+                &self.var_leaves[self.var_offsets[var] as usize..self.var_offsets[var + 1] as usize]
+            }
+            _ => &[],
+        }
+    }
+
+    /// Counts models under `assumptions`.
+    pub fn count(&self, assumptions: &[i32]) -> Integer {
+        self.count_with(assumptions, &mut vec![])
+    }
+
+    /// Counts models under `assumptions`, reusing `vals` as node value buffer.
+    ///
+    /// After the call `vals` holds the value of every node, which allows
+    /// repeated queries without reallocating big integers.
+    pub fn count_with(&self, assumptions: &[i32], vals: &mut Vec<Integer>) -> Integer {
+        let n = self.node_count();
+        if n == 0 {
+            return Integer::ZERO;
+        }
+        vals.resize(n, Integer::ZERO);
+
+        for (i, val) in vals.iter_mut().enumerate() {
+            if self.kinds[i] == LIT {
+                val.assign(self.vals[i]);
+            }
+        }
+        for lit in assumptions {
+            self.leaves_of(lit.unsigned_abs() as usize)
+                .iter()
+                .filter(|leaf| self.lits[**leaf as usize] == -lit)
+                .for_each(|leaf| vals[*leaf as usize].assign(0));
+        }
+
+        for i in 0..n {
+            let (done, rest) = vals.split_at_mut(i);
+            let val = &mut rest[0];
+            match self.kinds[i] {
+                AND => {
+                    val.assign(1);
+                    for child in self.children(i) {
+                        *val *= &done[*child as usize];
+                    }
+                }
+                OR => {
+                    val.assign(0);
+                    for child in self.children(i) {
+                        *val += &done[*child as usize];
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        vals[n - 1].clone()
+    }
+
+    /// Returns for every node whether it is a leaf falsified by `assumptions`.
+    pub fn falsified(&self, assumptions: &[i32]) -> Vec<bool> {
+        let mut falsified = vec![false; self.node_count()];
+        for lit in assumptions {
+            self.leaves_of(lit.unsigned_abs() as usize)
+                .iter()
+                .filter(|leaf| self.lits[**leaf as usize] == -lit)
+                .for_each(|leaf| falsified[*leaf as usize] = true);
+        }
+
+        falsified
+    }
+
+    /// Computes the partial derivative of the root with respect to every node
+    /// for node values `vals`, in one top-down pass.
+    pub fn derivatives(&self, vals: &[Integer]) -> Vec<Integer> {
+        let n = self.node_count();
+        let mut derivs = vec![Integer::ZERO; n];
+        if n == 0 {
+            return derivs;
+        }
+        derivs[n - 1].assign(1);
+
+        let mut suffix = vec![];
+        for i in (0..n).rev() {
+            let (below, rest) = derivs.split_at_mut(i);
+            let d = &rest[0];
+            if *d == 0 {
+                continue;
+            }
+            let children = self.children(i);
+            match self.kinds[i] {
+                OR => children
+                    .iter()
+                    .for_each(|child| below[*child as usize] += d),
+                AND => {
+                    // product of all siblings via prefix and suffix products
+                    suffix.clear();
+                    suffix.push(Integer::from(1));
+                    for child in children.iter().rev() {
+                        let p = Integer::from(suffix.last().unwrap() * &vals[*child as usize]);
+                        suffix.push(p);
+                    }
+                    let mut prefix = d.clone();
+                    for (k, child) in children.iter().enumerate() {
+                        let c = *child as usize;
+                        below[c] += Integer::from(&prefix * &suffix[children.len() - 1 - k]);
+                        prefix *= &vals[c];
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        derivs
+    }
+
+    /// Counts models under `assumptions` and, for every variable `v`, under
+    /// `assumptions` extended by `v` and by `-v` respectively.
+    ///
+    /// Uses one bottom-up and one top-down pass: by decomposability the
+    /// root is linear in every leaf, so the count under `l` is the count
+    /// minus the contribution of the leaves of `-l`.
+    pub fn marginals(&self, assumptions: &[i32]) -> (Integer, Vec<(Integer, Integer)>) {
+        let mut vals = vec![];
+        let count = self.count_with(assumptions, &mut vals);
+        let derivs = self.derivatives(&vals);
+
+        let marginals = (0..=self.var_count())
+            .map(|var| {
+                let (mut pos, mut neg) = (count.clone(), count.clone());
+                for leaf in self.leaves_of(var) {
+                    let leaf = *leaf as usize;
+                    let contribution = Integer::from(&derivs[leaf] * &vals[leaf]);
+                    match self.lits[leaf] > 0 {
+                        true => neg -= contribution,
+                        _ => pos -= contribution,
+                    }
+                }
+                (pos, neg)
+            })
+            .collect();
+
+        (count, marginals)
+    }
+
+    /// Reads a smooth d-DNNF in the c2d format, i.e., a header
+    /// `nnf <nodes> <edges> <vars>` followed by leaves `L <lit>`, and-nodes
+    /// `A <n> <c1>..<cn>` and or-nodes `O <j> <n> <c1>..<cn>`.
+    pub fn read_nnf(nnf: impl AsRef<Path>) -> Result<Self> {
+        Self::from_nnf(&read_to_string(nnf)?)
+    }
+
+    pub fn from_nnf(nnf: &str) -> Result<Self> {
+        let mut kinds = vec![];
+        let mut offsets = vec![0];
+        let mut children = vec![];
+        let mut lits = vec![];
+
+        for line in nnf.lines() {
+            let mut spec = line.split_whitespace();
+            match spec.next() {
+                Some("L") => {
+                    let lit = spec
+                        .next()
+                        .and_then(|l| i32::from_str(l).ok())
+                        .ok_or(CcgError::ReadingError("could not read literal.".to_owned()))?;
+                    kinds.push(LIT);
+                    lits.push(lit);
+                }
+                Some(gate @ ("A" | "O")) => {
+                    if gate == "O" {
+                        spec.next();
+                    }
+                    read_children(spec, kinds.len(), &mut children)?;
+                    kinds.push(if gate == "A" { AND } else { OR });
+                    lits.push(0);
+                }
+                _ => continue,
+            }
+            offsets.push(children.len() as u32);
+        }
+
+        let vals = vec![1; kinds.len()];
+        let mut graph = Self::from_nodes(
+            kinds,
+            offsets,
+            children,
+            lits,
+            vals,
+            HashMap::new(),
+            Weights::default(),
+        );
+        graph.log10_count = graph.count(&[]).to_f64().log10();
+
+        Ok(graph)
+    }
+
+    /// Builds a graph from its node arrays and indexes leaves by variable.
+    fn from_nodes(
+        kinds: Vec<u8>,
+        offsets: Vec<u32>,
+        children: Vec<u32>,
+        lits: Vec<i32>,
+        vals: Vec<i32>,
+        mapping: HashMap<String, i32>,
+        weights: Weights,
+    ) -> Self {
+        let var_count = lits
+            .iter()
+            .map(|l| l.unsigned_abs() as usize)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let mut var_offsets = vec![0u32; var_count + 1];
+        lits.iter()
+            .filter(|l| **l != 0)
+            .for_each(|l| var_offsets[l.unsigned_abs() as usize + 1] += 1);
+        for v in 0..var_count {
+            var_offsets[v + 1] += var_offsets[v];
+        }
+        let mut fill = var_offsets.clone();
+        let mut var_leaves = vec![0u32; var_offsets[var_count] as usize];
+        lits.iter()
+            .enumerate()
+            .filter(|(_, l)| **l != 0)
+            .for_each(|(i, l)| {
+                let v = l.unsigned_abs() as usize;
+                var_leaves[fill[v] as usize] = i as u32;
+                fill[v] += 1;
+            });
+
+        let edge_count = children.len();
+        Self {
+            kinds: kinds.into(),
+            offsets: offsets.into(),
+            children: children.into(),
+            lits: lits.into(),
+            vals: vals.into(),
+            var_offsets: var_offsets.into(),
+            var_leaves: var_leaves.into(),
+            mapping,
+            weights,
+            edge_count,
+            log10_count: 0.0,
+        }
+    }
+}
+
+impl FromStr for Ccg {
+    type Err = CcgError;
+
+    fn from_str(ccg: &str) -> Result<Self> {
+        let mut kinds = vec![];
+        let mut offsets = vec![0];
+        let mut children = vec![];
+        let mut lits = vec![];
+        let mut vals = vec![];
+        let mut mapping = HashMap::new();
+        let mut weights = Weights::default();
+        let mut log10_count = None;
+
+        for line in ccg.lines() {
+            let mut spec = line.split_whitespace();
+            match spec.next() {
+                Some("ccg") => {
+                    log10_count = spec.nth(3).and_then(|s| f64::from_str(s).ok());
+                }
+                Some("c") if line.starts_with("c p weight") => {
+                    let mut spec = spec.skip(2);
+                    weights.insert_spec(spec.next(), spec.next(), &mapping)?;
+                }
+                Some("c") => {
+                    let v = spec.next().and_then(|s| i32::from_str(s).ok()).ok_or(
+                        CcgError::ReadingError("could not read integer in mapping.".to_owned()),
+                    )?;
+                    let k = spec.next().ok_or(CcgError::ReadingError(
+                        "could not read atom in mapping.".to_owned(),
+                    ))?;
+                    mapping.insert(k.to_owned(), v);
+                }
+                Some(gate @ (SAND | SOR)) => {
+                    read_children(spec, kinds.len(), &mut children)?;
+                    kinds.push(if gate == SAND { AND } else { OR });
+                    offsets.push(children.len() as u32);
+                    lits.push(0);
+                    vals.push(0);
+                }
+                Some(lit) => {
+                    let lit = i32::from_str(lit).map_err(|_| {
+                        CcgError::ReadingError(format!("could not read literal {lit}."))
+                    })?;
+                    let val = spec.next().and_then(|s| i32::from_str(s).ok()).ok_or(
+                        CcgError::ReadingError("could not read literal value.".to_owned()),
+                    )?;
+                    kinds.push(LIT);
+                    offsets.push(children.len() as u32);
+                    lits.push(lit);
+                    vals.push(val);
+                }
+                None => (),
+            }
+        }
+
+        let mut graph = Self::from_nodes(kinds, offsets, children, lits, vals, mapping, weights);
+        graph.log10_count = match log10_count {
+            Some(c) => c,
+            None => graph.count(&[]).to_f64().log10(),
+        };
+
+        Ok(graph)
+    }
+}
+
+/// Reads `<n> <c1>..<cn>` of gate `node`, checking that children precede it.
+fn read_children<'a>(
+    mut spec: impl Iterator<Item = &'a str>,
+    node: usize,
+    children: &mut Vec<u32>,
+) -> Result<()> {
+    let n_children =
+        spec.next()
+            .and_then(|s| usize::from_str(s).ok())
+            .ok_or(CcgError::ReadingError(
+                "could not read child count.".to_owned(),
+            ))?;
+    let mut n_read = 0;
+    for child in spec {
+        match u32::from_str(child) {
+            Ok(c) if (c as usize) < node => children.push(c),
+            _ => {
+                return Err(CcgError::ReadingError(format!(
+                    "invalid child {child} of node {node}."
+                )))
+            }
+        }
+        n_read += 1;
+    }
+
+    match n_read == n_children {
+        true => Ok(()),
+        _ => Err(CcgError::ReadingError(format!(
+            "node {node} has {n_read} children, expected {n_children}."
+        ))),
+    }
+}
+
+/// Reads `literal` given as cnf literal, `a` or `~a`, resolving atoms by
+/// `mapping`.
+pub fn read_literal(literal: &str, mapping: &HashMap<String, i32>) -> Option<i32> {
+    if let Ok(lit) = i32::from_str(literal) {
+        return Some(lit);
+    }
+    match literal.strip_prefix('~') {
+        Some(atom) => mapping.get(atom).map(|i| -i),
+        _ => mapping.get(literal).copied(),
+    }
+}
+
+/// Returns `literals` as `a` or `~a` by `mapping`, or as integers if they
+/// have no atom.
+pub fn literal_names(literals: &[i32], mapping: &HashMap<String, i32>) -> Vec<String> {
+    let symbols = mapping
+        .iter()
+        .filter(|(_, var)| literals.iter().any(|l| l.abs() == **var))
+        .map(|(atom, var)| (*var, atom.as_str()))
+        .collect::<HashMap<_, _>>();
+
+    literals
+        .iter()
+        .map(|lit| match symbols.get(&lit.abs()) {
+            Some(atom) if *lit > 0 => atom.to_string(),
+            Some(atom) => format!("~{atom}"),
+            _ => lit.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rug::Rational;
+
+    #[test]
+    fn read_example() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.as.cnf.nnf.ccg")?;
+        assert_eq!(graph.node_count(), 29);
+        assert_eq!(graph.count(&[]), 6);
+        assert_eq!(graph.count(&[-9, 10]), 2);
+        assert_eq!(graph.leaves_of(10).len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn read_literals_by_name() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.as.cnf.nnf.ccg")?;
+        let literals = ["~i", "b", "-19", "7"]
+            .iter()
+            .map(|l| read_literal(l, graph.mapping()))
+            .collect::<Option<Vec<_>>>();
+        assert_eq!(literals, Some(vec![-3, 9, -19, 7]));
+        assert_eq!(read_literal("x", graph.mapping()), None);
+        assert_eq!(
+            literal_names(&[-3, 9, -19, 7], graph.mapping()),
+            vec!["~i", "b", "~e", "7"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reject_wrong_child_count() -> Result<()> {
+        let text = std::fs::read_to_string("examples/example.lp.sm.cnf.nnf.ccg")?;
+        assert!(text.parse::<Ccg>().is_ok());
+        assert!(text.replacen("* 2 2 3", "* 3 2 3", 1).parse::<Ccg>().is_err());
+        assert!(text.replacen("* 2 2 3", "* 2 2 3 1", 1).parse::<Ccg>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn count_with_buffer() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.sm.cnf.nnf.ccg")?;
+        let mut vals = vec![];
+        let all = graph.count_with(&[], &mut vals);
+        let pos = graph.count_with(&[10], &mut vals);
+        let neg = graph.count_with(&[-10], &mut vals);
+        assert_eq!(all, pos + neg);
+        Ok(())
+    }
+
+    #[test]
+    fn marginals_match_counts() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.sm.cnf.nnf.ccg")?;
+        for assumptions in [vec![], vec![-10], vec![5, -3]] {
+            let (count, marginals) = graph.marginals(&assumptions);
+            assert_eq!(count, graph.count(&assumptions));
+            for (var, (pos, neg)) in marginals.iter().enumerate().skip(1) {
+                let mut a = assumptions.clone();
+                a.push(var as i32);
+                assert_eq!(*pos, graph.count(&a));
+                a.pop();
+                a.push(-(var as i32));
+                assert_eq!(*neg, graph.count(&a));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn read_nnf_example() -> Result<()> {
+        let graph = Ccg::read_nnf("examples/example.lp.as.cnf.nnf")?;
+        assert_eq!(graph.node_count(), 211);
+        assert_eq!(graph.edge_count(), 251);
+        assert_eq!(graph.count(&[]), 6);
+        assert_eq!(graph.count(&[-9, 10]), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn binary_roundtrip() -> Result<()> {
+        let path = std::env::temp_dir().join("iascar_binary_roundtrip.ccgb");
+        let graph = Ccg::read("examples/example.lp.as.cnf.nnf.ccg")?;
+        graph.write_binary(&mut File::create(&path)?)?;
+
+        assert!(Ccg::is_binary(&path)?);
+        let mapped = Ccg::read(&path)?;
+        assert_eq!(mapped.node_count(), graph.node_count());
+        assert_eq!(mapped.mapping(), graph.mapping());
+        assert_eq!(mapped.count(&[]), 6);
+        assert_eq!(mapped.count(&[-9, 10]), 2);
+
+        let mut text = vec![];
+        mapped.write_text(&mut text)?;
+        let reparsed = String::from_utf8(text).unwrap().parse::<Ccg>()?;
+        assert_eq!(reparsed.count(&[-9, 10]), 2);
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn reject_corrupt_binary() -> Result<()> {
+        let path = std::env::temp_dir().join("iascar_corrupt_binary.ccgb");
+        let mut bytes = vec![];
+        Ccg::read("examples/example.lp.as.cnf.nnf.ccg")?.write_binary(&mut bytes)?;
+
+        // node count such that the section sizes overflow
+        let mut corrupt = bytes.clone();
+        corrupt[16..24].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&path, &corrupt)?;
+        assert!(Ccg::read(&path).is_err());
+
+        // first child of the first gate beyond the nodes, with valid checksum
+        let children = 64 + 32 + 4 * 30;
+        let mut corrupt = bytes.clone();
+        corrupt[children..children + 4].copy_from_slice(&1000u32.to_le_bytes());
+        let sum = binary::checksum(&corrupt[64..]);
+        corrupt[56..64].copy_from_slice(&sum.to_le_bytes());
+        std::fs::write(&path, &corrupt)?;
+        assert!(Ccg::read(&path).is_err());
+
+        // checksum is only checked on demand
+        let mut corrupt = bytes;
+        corrupt[56] ^= 1;
+        std::fs::write(&path, &corrupt)?;
+        assert!(Ccg::read(&path).is_ok());
+        assert!(Ccg::verify(&path).is_err());
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn weights_roundtrip() -> Result<()> {
+        let path = std::env::temp_dir().join("iascar_weights_roundtrip.ccgb");
+        let ccg = read_to_string("examples/example.lp.as.cnf.nnf.ccg")?;
+        let graph = (ccg + "c p weight 10 0.3 0\nc p weight -10 7/10 0\n").parse::<Ccg>()?;
+        assert_eq!(graph.weights().get(10), &Rational::from((3, 10)));
+        graph.write_binary(&mut File::create(&path)?)?;
+
+        let mapped = Ccg::read(&path)?;
+        assert_eq!(
+            mapped.weights().iter().collect::<Vec<_>>(),
+            graph.weights().iter().collect::<Vec<_>>()
+        );
+        let mut text = vec![];
+        mapped.write_text(&mut text)?;
+        let reparsed = String::from_utf8(text).unwrap().parse::<Ccg>()?;
+        assert_eq!(reparsed.weights().get(-10), &Rational::from((7, 10)));
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use std::cmp::Ordering;
+
+use super::semiring::Selective;
+use super::{Ccg, AND, LIT};
+
+/// Value of a partial model rooted at some node, together with the choices
+/// leading to it: the position of the child and its rank at or-nodes, and
+/// the rank of every child at and-nodes.
+type Candidate<E> = (E, Vec<u32>);
+
+#[allow(unused)]
+impl Ccg {
+    /// Returns up to `k` best models under `assumptions` in `semiring`, best
+    /// first, where `label(lit, val)` gives the value of each literal leaf.
+    ///
+    /// Keeps the `k` best partial models at every node in a bottom-up pass
+    /// and traces each of the best ones at the root back to its literals.
+    /// Models of value zero are omitted.
+    pub fn best_models<R: Selective>(
+        &self,
+        semiring: &R,
+        assumptions: &[i32],
+        label: impl Fn(i32, i32) -> R::Element,
+        k: usize,
+    ) -> Vec<(R::Element, Vec<i32>)>
+    where
+        R::Element: PartialEq,
+    {
+        let n = self.node_count();
+        if n == 0 || k == 0 {
+            return vec![];
+        }
+
+        let zero = semiring.zero();
+        let order = |a: &Candidate<R::Element>, b: &Candidate<R::Element>| -> Ordering {
+            semiring.preference(&a.0, &b.0)
+        };
+        let falsified = self.falsified(assumptions);
+        let mut best: Vec<Vec<Candidate<R::Element>>> = Vec::with_capacity(n);
+
+        for (node, falsified) in falsified.iter().enumerate() {
+            let mut candidates = match self.kinds[node] {
+                LIT if *falsified => vec![],
+                LIT => vec![(label(self.lits[node], self.vals[node]), vec![])],
+                AND => {
+                    let mut acc = vec![(semiring.one(), vec![])];
+                    for child in self.children(node) {
+                        let mut next = Vec::with_capacity(acc.len() * k);
+                        for (val, ranks) in &acc {
+                            for (rank, (child_val, _)) in best[*child as usize].iter().enumerate() {
+                                let mut val = val.clone();
+                                semiring.mul(&mut val, child_val);
+                                let mut ranks = ranks.clone();
+                                ranks.push(rank as u32);
+                                next.push((val, ranks));
+                            }
+                        }
+                        next.sort_by(order);
+                        next.truncate(k);
+                        acc = next;
+                    }
+                    acc
+                }
+                _ => {
+                    let mut candidates =
+                        self.children(node)
+                            .iter()
+                            .enumerate()
+                            .flat_map(|(pos, child)| {
+                                best[*child as usize].iter().enumerate().map(
+                                    move |(rank, (val, _))| {
+                                        (val.clone(), vec![pos as u32, rank as u32])
+                                    },
+                                )
+                            })
+                            .collect::<Vec<_>>();
+                    candidates.sort_by(order);
+                    candidates.truncate(k);
+                    candidates
+                }
+            };
+            candidates.retain(|(val, _)| *val != zero);
+            best.push(candidates);
+        }
+
+        let root = n - 1;
+        (0..best[root].len())
+            .map(|rank| {
+                let mut model = vec![];
+                let mut stack = vec![(root, rank)];
+                while let Some((node, rank)) = stack.pop() {
+                    let choice = &best[node][rank].1;
+                    match self.kinds[node] {
+                        LIT => model.push(self.lits[node]),
+                        AND => stack.extend(
+                            self.children(node)
+                                .iter()
+                                .zip(choice)
+                                .map(|(child, rank)| (*child as usize, *rank as usize)),
+                        ),
+                        _ => stack.push((
+                            self.children(node)[choice[0] as usize] as usize,
+                            choice[1] as usize,
+                        )),
+                    }
+                }
+                model.sort_by_key(|l| l.abs());
+
+                (best[root][rank].0.clone(), model)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ccg::{MaxProduct, MinSum, Result, Weights};
+    use rug::Rational;
+
+    #[test]
+    fn best_models_are_ordered_and_distinct() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.as.cnf.nnf.ccg")?;
+        let weights = Weights::parse("a 1/3\n~a 2/3\nb 1/4\nc 3\n", graph.mapping())?;
+        let label = |lit, _| weights.get(lit).clone();
+
+        let models = graph.best_models(&MaxProduct, &[], label, 10);
+        assert_eq!(models.len(), 6);
+        assert_eq!(
+            models[0].0,
+            graph.evaluate(&MaxProduct, &[], |lit, _| weights.get(lit).clone())
+        );
+        for pair in models.windows(2) {
+            assert!(pair[0].0 >= pair[1].0);
+            assert_ne!(pair[0].1, pair[1].1);
+        }
+        for (val, model) in &models {
+            assert_eq!(graph.count(model), 1);
+            let product = model
+                .iter()
+                .fold(Rational::from(1), |acc, lit| acc * weights.get(*lit));
+            assert_eq!(*val, product);
+        }
+
+        let under = graph.best_models(&MaxProduct, &[-9, 10], label, 10);
+        assert_eq!(under.len(), 2);
+        assert!(under
+            .iter()
+            .all(|(_, m)| m.contains(&-9) && m.contains(&10)));
+        Ok(())
+    }
+
+    #[test]
+    fn min_cost_model() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.as.cnf.nnf.ccg")?;
+        let cost = |lit: i32, _| Some(Rational::from(lit > 0));
+        let models = graph.best_models(&MinSum, &[], cost, 1);
+        assert_eq!(models[0].0, graph.evaluate(&MinSum, &[], cost));
+        assert_eq!(
+            models[0].0,
+            Some(Rational::from(
+                models[0].1.iter().filter(|l| **l > 0).count()
+            ))
+        );
+        Ok(())
+    }
+}
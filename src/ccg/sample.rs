@@ -0,0 +1,162 @@
+use rug::rand::RandState;
+use rug::{Integer, Rational};
+
+use super::semiring::SumProduct;
+use super::{Ccg, Weights, LIT, OR};
+
+/// Draws independent models of a [`Ccg`] under fixed assumptions.
+///
+/// Every or-node picks a child with probability proportional to the child's
+/// (weighted) count, so models are drawn top-down without rejection. The
+/// counts are computed once on construction.
+#[allow(unused)]
+#[derive(Debug, Clone)]
+pub struct Sampler<'a> {
+    graph: &'a Ccg,
+    /// Integer choice weight of every or-edge, indexed like `children`.
+    weights: Vec<Integer>,
+    /// Sum of the choice weights of every or-node.
+    totals: Vec<Integer>,
+    satisfiable: bool,
+}
+
+#[allow(unused)]
+impl<'a> Sampler<'a> {
+    /// Samples models under `assumptions` uniformly at random.
+    pub fn uniform(graph: &'a Ccg, assumptions: &[i32]) -> Self {
+        let mut vals = vec![];
+        graph.count_with(assumptions, &mut vals);
+
+        let mut weights = vec![Integer::new(); graph.edge_count()];
+        let mut totals = vec![Integer::new(); graph.node_count()];
+        for node in (0..graph.node_count()).filter(|n| graph.kinds[*n] == OR) {
+            let offset = graph.offsets[node] as usize;
+            for (i, child) in graph.children(node).iter().enumerate() {
+                weights[offset + i].clone_from(&vals[*child as usize]);
+                totals[node] += &vals[*child as usize];
+            }
+        }
+
+        Self {
+            graph,
+            weights,
+            totals,
+            satisfiable: vals.last().is_some_and(|v| *v > 0),
+        }
+    }
+
+    /// Samples models under `assumptions` with probability proportional to
+    /// the product of their literal `weights`.
+    pub fn weighted(graph: &'a Ccg, assumptions: &[i32], weights: &Weights) -> Self {
+        let vals = graph.evaluate_nodes(&SumProduct, assumptions, |lit, val| {
+            Rational::from(val) * weights.get(lit)
+        });
+
+        let mut edge_weights = vec![Integer::new(); graph.edge_count()];
+        let mut totals = vec![Integer::new(); graph.node_count()];
+        for node in (0..graph.node_count()).filter(|n| graph.kinds[*n] == OR) {
+            let children = graph.children(node);
+            // scale rational child weights to integers by their common denominator
+            let denominator = children.iter().fold(Integer::from(1), |acc, child| {
+                acc.lcm(vals[*child as usize].denom())
+            });
+            let offset = graph.offsets[node] as usize;
+            for (i, child) in children.iter().enumerate() {
+                let val = &vals[*child as usize];
+                let weight = Integer::from(&denominator / val.denom()) * val.numer();
+                totals[node] += &weight;
+                edge_weights[offset + i] = weight;
+            }
+        }
+
+        Self {
+            graph,
+            weights: edge_weights,
+            totals,
+            satisfiable: vals.last().is_some_and(|v| *v != 0),
+        }
+    }
+
+    /// Returns whether there is a model to draw.
+    pub fn is_satisfiable(&self) -> bool {
+        self.satisfiable
+    }
+
+    /// Draws a model as literals ordered by variable, or `None` if there is
+    /// no model.
+    pub fn sample(&self, rng: &mut RandState) -> Option<Vec<i32>> {
+        if !self.satisfiable {
+            return None;
+        }
+
+        let graph = self.graph;
+        let mut model = vec![];
+        let mut stack = vec![graph.root()];
+        while let Some(node) = stack.pop() {
+            match graph.kinds[node] {
+                LIT => model.push(graph.lits[node]),
+                OR => {
+                    let mut r = self.totals[node].clone().random_below(rng);
+                    let offset = graph.offsets[node] as usize;
+                    for (i, child) in graph.children(node).iter().enumerate() {
+                        if r < self.weights[offset + i] {
+                            stack.push(*child as usize);
+                            break;
+                        }
+                        r -= &self.weights[offset + i];
+                    }
+                }
+                _ => stack.extend(graph.children(node).iter().map(|c| *c as usize)),
+            }
+        }
+        model.sort_by_key(|l| l.abs());
+
+        Some(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ccg::Result;
+    use std::collections::HashMap;
+
+    #[test]
+    fn uniform_samples_are_models() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.as.cnf.nnf.ccg")?;
+        let sampler = Sampler::uniform(&graph, &[-9]);
+        let mut rng = RandState::new();
+        rng.seed(&Integer::from(7));
+
+        let mut seen = HashMap::<_, usize>::new();
+        for _ in 0..3000 {
+            let model = sampler.sample(&mut rng).expect("satisfiable");
+            assert!(model.contains(&-9));
+            assert_eq!(graph.count(&model), 1);
+            *seen.entry(model).or_insert(0) += 1;
+        }
+        assert_eq!(Integer::from(seen.len()), graph.count(&[-9]));
+        let expected = 3000 / seen.len();
+        assert!(seen.values().all(|n| n.abs_diff(expected) < expected / 10));
+
+        assert!(!Sampler::uniform(&graph, &[9, -9]).is_satisfiable());
+        Ok(())
+    }
+
+    #[test]
+    fn weighted_samples_follow_weights() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.as.cnf.nnf.ccg")?;
+        let weights = Weights::parse("a 1/4\n~a 3/4\n", graph.mapping())?;
+        let sampler = Sampler::weighted(&graph, &[], &weights);
+        let mut rng = RandState::new();
+        rng.seed(&Integer::from(7));
+
+        let expected = graph.weighted_count(&[10], &weights) / graph.weighted_count(&[], &weights);
+        let n = 4000;
+        let hits = (0..n)
+            .filter(|_| sampler.sample(&mut rng).expect("satisfiable").contains(&10))
+            .count();
+        assert!((hits as f64 / n as f64 - expected.to_f64()).abs() < 0.03);
+        Ok(())
+    }
+}
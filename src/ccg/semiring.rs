@@ -0,0 +1,408 @@
+//! Algebraic model counting.
+//!
+//! A [`Semiring`] fixes how the values of children are combined at or-nodes
+//! (`add`) and and-nodes (`mul`). Evaluating a graph in a semiring after
+//! labelling its literal leaves generalises counting (the default, see
+//! [`Ccg::count`]) to satisfiability, weighted counting, most probable models,
+//! minimum cost models and gradients.
+
+use rug::{Float, Integer, Rational};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use super::{Ccg, AND, LIT};
+
+/// Commutative semiring over `Element`.
+///
+/// Semirings are values rather than marker types, so that they can carry
+/// parameters such as the precision of floats.
+pub trait Semiring {
+    type Element: Clone;
+
+    fn zero(&self) -> Self::Element;
+    fn one(&self) -> Self::Element;
+    fn add(&self, acc: &mut Self::Element, x: &Self::Element);
+    fn mul(&self, acc: &mut Self::Element, x: &Self::Element);
+}
+
+/// Semiring whose addition selects one of its arguments, such that the best
+/// model of a graph can be traced back.
+pub trait Selective: Semiring {
+    /// Orders `a` before `b` if `a` is preferred.
+    fn preference(&self, a: &Self::Element, b: &Self::Element) -> Ordering;
+}
+
+/// Model counting over arbitrary precision integers.
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Counting;
+
+impl Semiring for Counting {
+    type Element = Integer;
+
+    fn zero(&self) -> Integer {
+        Integer::new()
+    }
+    fn one(&self) -> Integer {
+        Integer::from(1)
+    }
+    fn add(&self, acc: &mut Integer, x: &Integer) {
+        *acc += x;
+    }
+    fn mul(&self, acc: &mut Integer, x: &Integer) {
+        *acc *= x;
+    }
+}
+
+/// Model counting on graphs that need not be smooth.
+///
+/// Elements pair a count with the variables below a node. Adding counts
+/// both summands over the union of their variables, i.e., shifts each count
+/// by the gap of variables it does not mention.
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GapCounting;
+
+impl Semiring for GapCounting {
+    type Element = (Integer, HashSet<u32>);
+
+    fn zero(&self) -> Self::Element {
+        (Integer::new(), HashSet::new())
+    }
+    fn one(&self) -> Self::Element {
+        (Integer::from(1), HashSet::new())
+    }
+    fn add(&self, acc: &mut Self::Element, x: &Self::Element) {
+        let gap = x.1.difference(&acc.1).count();
+        acc.0 <<= gap;
+        acc.0 += Integer::from(&x.0 << (acc.1.len() + gap - x.1.len()));
+        acc.1.extend(&x.1);
+    }
+    fn mul(&self, acc: &mut Self::Element, x: &Self::Element) {
+        acc.0 *= &x.0;
+        acc.1.extend(&x.1);
+    }
+}
+
+/// Satisfiability.
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Boolean;
+
+impl Semiring for Boolean {
+    type Element = bool;
+
+    fn zero(&self) -> bool {
+        false
+    }
+    fn one(&self) -> bool {
+        true
+    }
+    fn add(&self, acc: &mut bool, x: &bool) {
+        *acc |= *x;
+    }
+    fn mul(&self, acc: &mut bool, x: &bool) {
+        *acc &= *x;
+    }
+}
+
+/// Exact weighted model counting.
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SumProduct;
+
+impl Semiring for SumProduct {
+    type Element = Rational;
+
+    fn zero(&self) -> Rational {
+        Rational::new()
+    }
+    fn one(&self) -> Rational {
+        Rational::from(1)
+    }
+    fn add(&self, acc: &mut Rational, x: &Rational) {
+        *acc += x;
+    }
+    fn mul(&self, acc: &mut Rational, x: &Rational) {
+        *acc *= x;
+    }
+}
+
+/// Weighted model counting with floats of precision `prec` bits.
+#[allow(unused)]
+#[derive(Debug, Clone, Copy)]
+pub struct FloatSumProduct {
+    pub prec: u32,
+}
+
+impl Semiring for FloatSumProduct {
+    type Element = Float;
+
+    fn zero(&self) -> Float {
+        Float::new(self.prec)
+    }
+    fn one(&self) -> Float {
+        Float::with_val(self.prec, 1)
+    }
+    fn add(&self, acc: &mut Float, x: &Float) {
+        *acc += x;
+    }
+    fn mul(&self, acc: &mut Float, x: &Float) {
+        *acc *= x;
+    }
+}
+
+/// Weight of a most probable model, for non-negative weights.
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MaxProduct;
+
+impl Semiring for MaxProduct {
+    type Element = Rational;
+
+    fn zero(&self) -> Rational {
+        Rational::new()
+    }
+    fn one(&self) -> Rational {
+        Rational::from(1)
+    }
+    fn add(&self, acc: &mut Rational, x: &Rational) {
+        if *x > *acc {
+            *acc = x.clone();
+        }
+    }
+    fn mul(&self, acc: &mut Rational, x: &Rational) {
+        *acc *= x;
+    }
+}
+
+impl Selective for MaxProduct {
+    fn preference(&self, a: &Rational, b: &Rational) -> Ordering {
+        b.cmp(a)
+    }
+}
+
+/// Cost of a minimum cost model, where `None` is infinite cost.
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MinSum;
+
+impl Semiring for MinSum {
+    type Element = Option<Rational>;
+
+    fn zero(&self) -> Option<Rational> {
+        None
+    }
+    fn one(&self) -> Option<Rational> {
+        Some(Rational::new())
+    }
+    fn add(&self, acc: &mut Option<Rational>, x: &Option<Rational>) {
+        match (acc.as_ref(), x) {
+            (_, None) => (),
+            (Some(a), Some(b)) if a <= b => (),
+            _ => *acc = x.clone(),
+        }
+    }
+    fn mul(&self, acc: &mut Option<Rational>, x: &Option<Rational>) {
+        match (acc.as_mut(), x) {
+            (Some(a), Some(b)) => *a += b,
+            _ => *acc = None,
+        }
+    }
+}
+
+impl Selective for MinSum {
+    fn preference(&self, a: &Option<Rational>, b: &Option<Rational>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+/// Weighted model count together with its derivative with respect to one
+/// parameter, i.e., pairs `(p, p')` with `(a, a') * (b, b') = (ab, ab' + a'b)`.
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Gradient;
+
+impl Semiring for Gradient {
+    type Element = (Rational, Rational);
+
+    fn zero(&self) -> Self::Element {
+        (Rational::new(), Rational::new())
+    }
+    fn one(&self) -> Self::Element {
+        (Rational::from(1), Rational::new())
+    }
+    fn add(&self, acc: &mut Self::Element, x: &Self::Element) {
+        acc.0 += &x.0;
+        acc.1 += &x.1;
+    }
+    fn mul(&self, acc: &mut Self::Element, x: &Self::Element) {
+        acc.1 *= &x.0;
+        acc.1 += Rational::from(&acc.0 * &x.1);
+        acc.0 *= &x.0;
+    }
+}
+
+#[allow(unused)]
+impl Ccg {
+    /// Evaluates the graph in `semiring` under `assumptions`, where
+    /// `label(lit, val)` gives the value of each literal leaf that is not
+    /// falsified by `assumptions`.
+    pub fn evaluate<R: Semiring>(
+        &self,
+        semiring: &R,
+        assumptions: &[i32],
+        label: impl Fn(i32, i32) -> R::Element,
+    ) -> R::Element {
+        self.evaluate_nodes(semiring, assumptions, label)
+            .pop()
+            .unwrap_or_else(|| semiring.zero())
+    }
+
+    /// Like [`Ccg::evaluate`], but returns the value of every node.
+    pub fn evaluate_nodes<R: Semiring>(
+        &self,
+        semiring: &R,
+        assumptions: &[i32],
+        label: impl Fn(i32, i32) -> R::Element,
+    ) -> Vec<R::Element> {
+        let falsified = self.falsified(assumptions);
+        let mut vals: Vec<R::Element> = Vec::with_capacity(self.node_count());
+
+        for (node, falsified) in falsified.iter().enumerate() {
+            let val = match self.kinds[node] {
+                LIT if *falsified => semiring.zero(),
+                LIT => label(self.lits[node], self.vals[node]),
+                AND => {
+                    let mut val = semiring.one();
+                    for child in self.children(node) {
+                        semiring.mul(&mut val, &vals[*child as usize]);
+                    }
+                    val
+                }
+                _ => {
+                    let mut val = semiring.zero();
+                    for child in self.children(node) {
+                        semiring.add(&mut val, &vals[*child as usize]);
+                    }
+                    val
+                }
+            };
+            vals.push(val);
+        }
+
+        vals
+    }
+
+    /// Computes the partial derivative of the root with respect to every node
+    /// for node values `vals` in `semiring`, in one top-down pass.
+    pub fn derivatives_in<R: Semiring>(
+        &self,
+        semiring: &R,
+        vals: &[R::Element],
+    ) -> Vec<R::Element> {
+        let n = self.node_count();
+        let mut derivs = vec![semiring.zero(); n];
+        if n == 0 {
+            return derivs;
+        }
+        derivs[n - 1] = semiring.one();
+
+        let mut suffix = vec![];
+        for i in (0..n).rev() {
+            let (below, rest) = derivs.split_at_mut(i);
+            let d = &rest[0];
+            let children = self.children(i);
+            match self.kinds[i] {
+                AND => {
+                    // product of all siblings via prefix and suffix products
+                    suffix.clear();
+                    suffix.push(semiring.one());
+                    for child in children.iter().rev() {
+                        let mut p = suffix.last().unwrap().clone();
+                        semiring.mul(&mut p, &vals[*child as usize]);
+                        suffix.push(p);
+                    }
+                    let mut prefix = d.clone();
+                    for (k, child) in children.iter().enumerate() {
+                        let c = *child as usize;
+                        let mut p = prefix.clone();
+                        semiring.mul(&mut p, &suffix[children.len() - 1 - k]);
+                        semiring.add(&mut below[c], &p);
+                        semiring.mul(&mut prefix, &vals[c]);
+                    }
+                }
+                LIT => (),
+                _ => children
+                    .iter()
+                    .for_each(|child| semiring.add(&mut below[*child as usize], d)),
+            }
+        }
+
+        derivs
+    }
+
+    /// Returns whether the graph has a model under `assumptions`.
+    pub fn is_satisfiable(&self, assumptions: &[i32]) -> bool {
+        self.evaluate(&Boolean, assumptions, |_, val| val != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ccg::{Result, Weights};
+
+    #[test]
+    fn semirings_agree_with_count() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.sm.cnf.nnf.ccg")?;
+        for assumptions in [vec![], vec![-10], vec![10, -10]] {
+            let count = graph.count(&assumptions);
+            assert_eq!(
+                graph.evaluate(&Counting, &assumptions, |_, val| Integer::from(val)),
+                count
+            );
+            assert_eq!(graph.is_satisfiable(&assumptions), count > 0);
+        }
+
+        // every positive literal costs 1: the smallest answer set is {i, a, e}
+        let cost = graph.evaluate(&MinSum, &[], |lit, _| Some(Rational::from(lit > 0)));
+        assert_eq!(cost, Some(Rational::from(3)));
+        Ok(())
+    }
+
+    #[test]
+    fn gaps_count_unsmooth_graph() -> Result<()> {
+        // (1 & 2) | -1 over variables 1, 2 and 3
+        let graph = Ccg::from_nnf("nnf 5 4 3\nL 1\nL 2\nA 2 0 1\nL -1\nO 1 2 2 3\n")?;
+        let label = |lit: i32, val| (Integer::from(val), HashSet::from([lit.unsigned_abs()]));
+        let (count, vars) = graph.evaluate(&GapCounting, &[], label);
+        assert_eq!((count, vars.len()), (Integer::from(3), 2));
+        let (count, vars) = graph.evaluate(&GapCounting, &[1], label);
+        assert_eq!((count, vars.len()), (Integer::from(1), 2));
+        Ok(())
+    }
+
+    #[test]
+    fn gradient_of_weighted_count() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.as.cnf.nnf.ccg")?;
+        let weights = Weights::parse("a 1/3\n~a 2/3\nb 1/4\n", graph.mapping())?;
+        let (wmc, grad) = graph.evaluate(&Gradient, &[], |lit, val| {
+            let w = Rational::from(val) * weights.get(lit);
+            let dw = Rational::from(val * (lit == 10) as i32);
+            (w, dw)
+        });
+        assert_eq!(wmc, graph.weighted_count(&[], &weights));
+        // weighted count is linear in the weight of a
+        assert_eq!(
+            grad * weights.get(10),
+            graph.weighted_count(&[10], &weights)
+        );
+        Ok(())
+    }
+}
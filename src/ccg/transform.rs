@@ -0,0 +1,198 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{Ccg, LIT, OR};
+
+#[allow(unused)]
+impl Ccg {
+    /// Returns a graph whose models are exactly the models under
+    /// `assumptions`.
+    ///
+    /// Zeros of falsified leaves are propagated upwards, dead and unreachable
+    /// nodes are removed and gates left with a single child are replaced by
+    /// that child. Atoms no longer occurring in the graph are dropped from the
+    /// mapping. If there is no model, the result is a single empty or-node.
+    pub fn condition(&self, assumptions: &[i32]) -> Ccg {
+        self.reduce(assumptions, false)
+    }
+
+    /// Returns an equivalent graph without redundant structure.
+    ///
+    /// Besides removing zero-count branches and single-child gates like
+    /// [`Ccg::condition`], isomorphic nodes are merged (hash-consing) and
+    /// gates are flattened into parents of the same type if they have no
+    /// other parent. Children keep their order, so models are enumerated in
+    /// the same order as in the original graph.
+    pub fn minimize(&self) -> Ccg {
+        self.reduce(&[], true)
+    }
+
+    fn reduce(&self, assumptions: &[i32], merge: bool) -> Ccg {
+        let n = self.node_count();
+        let mut vals = vec![];
+        if n == 0 || self.count_with(assumptions, &mut vals) == 0 {
+            return self.rebuild(&[], vec![]);
+        }
+
+        let mut parents = vec![0u32; n];
+        for node in (0..n).filter(|i| vals[*i] > 0) {
+            for child in self.children(node) {
+                parents[*child as usize] += 1;
+            }
+        }
+
+        // kept children of every node; gates with a single child and nodes
+        // isomorphic to an earlier one redirect to it
+        let mut redirect = (0..n as u32).collect::<Vec<_>>();
+        let mut kept: Vec<Vec<u32>> = vec![vec![]; n];
+        let mut unique = HashMap::new();
+        for node in (0..n).filter(|i| vals[*i] > 0) {
+            let kind = self.kinds[node];
+            let mut children = vec![];
+            if kind != LIT {
+                for child in self
+                    .children(node)
+                    .iter()
+                    .filter(|c| vals[**c as usize] > 0)
+                {
+                    let child = redirect[*child as usize];
+                    let c = child as usize;
+                    match merge && self.kinds[c] == kind && parents[c] == 1 {
+                        true => children.extend_from_slice(&kept[c]),
+                        _ => children.push(child),
+                    }
+                }
+                if children.len() == 1 {
+                    redirect[node] = children[0];
+                    continue;
+                }
+            }
+            if merge {
+                let key = (kind, self.lits[node], self.vals[node], children.clone());
+                if let Some(first) = unique.get(&key) {
+                    redirect[node] = *first;
+                    continue;
+                }
+                unique.insert(key, node as u32);
+            }
+            kept[node] = children;
+        }
+
+        self.rebuild(&kept, vec![redirect[n - 1]])
+    }
+
+    /// Collects the nodes reachable from `roots` via `kept` children and
+    /// builds a graph of them, numbered in their original order. Mapping and
+    /// weights are restricted to variables that still occur. Without roots,
+    /// the graph is a single empty or-node.
+    fn rebuild(&self, kept: &[Vec<u32>], roots: Vec<u32>) -> Ccg {
+        if roots.is_empty() {
+            let mut graph = Ccg::from_nodes(
+                vec![OR],
+                vec![0, 0],
+                vec![],
+                vec![0],
+                vec![0],
+                Default::default(),
+                Default::default(),
+            );
+            graph.log10_count = f64::NEG_INFINITY;
+            return graph;
+        }
+
+        let mut reachable = vec![false; self.node_count()];
+        let mut stack = roots;
+        while let Some(node) = stack.pop() {
+            let node = node as usize;
+            if !reachable[node] {
+                reachable[node] = true;
+                stack.extend(&kept[node]);
+            }
+        }
+
+        let mut ids = vec![0u32; self.node_count()];
+        let (mut kinds, mut offsets, mut children, mut lits, mut vals) =
+            (vec![], vec![0], vec![], vec![], vec![]);
+        for node in (0..self.node_count()).filter(|i| reachable[*i]) {
+            ids[node] = kinds.len() as u32;
+            kinds.push(self.kinds[node]);
+            lits.push(self.lits[node]);
+            vals.push(self.vals[node]);
+            children.extend(kept[node].iter().map(|c| ids[*c as usize]));
+            offsets.push(children.len() as u32);
+        }
+
+        let vars = lits
+            .iter()
+            .map(|l: &i32| l.unsigned_abs() as i32)
+            .collect::<HashSet<_>>();
+        let mapping = self
+            .mapping
+            .iter()
+            .filter(|(_, v)| vars.contains(*v))
+            .map(|(a, v)| (a.clone(), *v))
+            .collect();
+        let mut weights = self.weights.clone();
+        weights.retain(|lit| vars.contains(&lit.abs()));
+
+        let mut graph = Ccg::from_nodes(kinds, offsets, children, lits, vals, mapping, weights);
+        graph.log10_count = graph.count(&[]).to_f64().log10();
+
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ccg::Result;
+
+    #[test]
+    fn conditioned_graph_keeps_models() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.sm.cnf.nnf.ccg")?;
+        for assumptions in [vec![-10], vec![5, -3], vec![10, 9]] {
+            let conditioned = graph.condition(&assumptions);
+            assert!(conditioned.node_count() < graph.node_count());
+            assert_eq!(conditioned.count(&[]), graph.count(&assumptions));
+            for var in 1..=graph.var_count() as i32 {
+                let mut a = assumptions.clone();
+                a.push(var);
+                assert_eq!(conditioned.count(&[var]), graph.count(&a));
+            }
+
+            let mut text = vec![];
+            conditioned.write_text(&mut text)?;
+            let reparsed = String::from_utf8(text).unwrap().parse::<Ccg>()?;
+            assert_eq!(reparsed.count(&[]), graph.count(&assumptions));
+        }
+
+        let empty = graph.condition(&[10, -10]);
+        assert_eq!(empty.node_count(), 1);
+        assert_eq!(empty.count(&[]), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn minimized_graph_is_equivalent() -> Result<()> {
+        for path in [
+            "examples/example.lp.sm.cnf.nnf.ccg",
+            "examples/example.lp.as.cnf.nnf.ccg",
+        ] {
+            let graph = Ccg::read(path)?;
+            let minimized = graph.minimize();
+            assert!(minimized.node_count() <= graph.node_count());
+            assert!(minimized.edge_count() <= graph.edge_count());
+            for var in 1..=graph.var_count() as i32 {
+                assert_eq!(minimized.count(&[var]), graph.count(&[var]));
+                assert_eq!(minimized.count(&[-var]), graph.count(&[-var]));
+            }
+            assert!(minimized.models(&[]).eq(graph.models(&[])));
+            assert_eq!(minimized.minimize().node_count(), minimized.node_count());
+        }
+
+        let graph = Ccg::read_nnf("examples/example.lp.as.cnf.nnf")?;
+        let minimized = graph.minimize();
+        assert!(minimized.node_count() < graph.node_count());
+        assert_eq!(minimized.count(&[]), graph.count(&[]));
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+use rug::{Float, Integer, Rational};
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::semiring::{FloatSumProduct, SumProduct};
+use super::{Ccg, CcgError, Result};
+
+/// Literal weights; literals without a weight have weight 1.
+#[derive(Debug, Clone, Default)]
+pub struct Weights {
+    weights: HashMap<i32, Rational>,
+}
+
+#[allow(unused)]
+impl Weights {
+    /// Reads weights from a file.
+    ///
+    /// Files starting with `p cnf` are read as MC-competition cnfs, taking
+    /// only `c p weight <lit> <weight> 0` lines. Otherwise every line is
+    /// either such a weight line, `w <lit> <weight>` or `<lit> <weight>`,
+    /// where `<lit>` is a cnf literal or an atom `a`/`~a` from `mapping`.
+    /// Lines starting with `c` are comments.
+    pub fn read(path: impl AsRef<Path>, mapping: &HashMap<String, i32>) -> Result<Self> {
+        let s = read_to_string(path)?;
+        match s.trim_start().starts_with("p cnf") {
+            true => Self::from_cnf(&s),
+            _ => Self::parse(&s, mapping),
+        }
+    }
+
+    /// Collects `c p weight` lines of a cnf.
+    pub fn from_cnf(cnf: &str) -> Result<Self> {
+        let mut weights = Self::default();
+        for line in cnf.lines().filter(|l| l.starts_with("c p weight")) {
+            let mut spec = line.split_whitespace().skip(3);
+            weights.insert_spec(spec.next(), spec.next(), &HashMap::new())?;
+        }
+
+        Ok(weights)
+    }
+
+    pub fn parse(s: &str, mapping: &HashMap<String, i32>) -> Result<Self> {
+        let mut weights = Self::default();
+        for line in s.lines() {
+            let mut spec = line.split_whitespace().peekable();
+            match spec.peek().copied() {
+                Some("c") if line.starts_with("c p weight") => {
+                    let mut spec = spec.skip(3);
+                    weights.insert_spec(spec.next(), spec.next(), mapping)?;
+                }
+                Some("c") | Some("p") | None => (),
+                Some("w") => {
+                    spec.next();
+                    weights.insert_spec(spec.next(), spec.next(), mapping)?;
+                }
+                _ => weights.insert_spec(spec.next(), spec.next(), mapping)?,
+            }
+        }
+
+        Ok(weights)
+    }
+
+    pub fn insert(&mut self, lit: i32, weight: Rational) {
+        self.weights.insert(lit, weight);
+    }
+
+    pub fn get(&self, lit: i32) -> &Rational {
+        self.weights.get(&lit).unwrap_or(Rational::ONE)
+    }
+
+    /// Returns the weight of `lit` if one was given.
+    pub fn weight(&self, lit: i32) -> Option<&Rational> {
+        self.weights.get(&lit)
+    }
+
+    /// Keeps only the weights of literals satisfying `f`.
+    pub fn retain(&mut self, mut f: impl FnMut(i32) -> bool) {
+        self.weights.retain(|lit, _| f(*lit));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weights.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.weights.len()
+    }
+
+    /// Iterates over weighted literals ordered by variable.
+    pub fn iter(&self) -> impl Iterator<Item = (i32, &Rational)> {
+        let mut weights = self
+            .weights
+            .iter()
+            .map(|(l, w)| (*l, w))
+            .collect::<Vec<_>>();
+        weights.sort_by_key(|(l, _)| (l.abs(), *l < 0));
+        weights.into_iter()
+    }
+
+    pub(super) fn insert_spec(
+        &mut self,
+        lit: Option<&str>,
+        weight: Option<&str>,
+        mapping: &HashMap<String, i32>,
+    ) -> Result<()> {
+        let lit = lit
+            .and_then(|l| {
+                i32::from_str(l).ok().or_else(|| match l.strip_prefix('~') {
+                    Some(a) => mapping.get(a).map(|i| -i),
+                    _ => mapping.get(l).copied(),
+                })
+            })
+            .ok_or(CcgError::ReadingError(format!(
+                "could not read weighted literal {lit:?}."
+            )))?;
+        let weight = weight
+            .and_then(parse_weight)
+            .ok_or(CcgError::ReadingError(format!(
+                "could not read weight of literal {lit}."
+            )))?;
+        self.insert(lit, weight);
+
+        Ok(())
+    }
+}
+
+/// Largest absolute exponent accepted in scientific notation.
+const MAX_EXPONENT: i32 = 1 << 16;
+
+/// Parses `p/q`, decimal and scientific notation exactly.
+///
+/// Exponents beyond `MAX_EXPONENT` are rejected, since the weight would be
+/// expanded to an exact fraction.
+pub fn parse_weight(s: &str) -> Option<Rational> {
+    if s.contains('/') {
+        return Rational::from_str(s).ok();
+    }
+
+    let (mantissa, exp) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], i32::from_str(&s[i + 1..]).ok()?),
+        _ => (s, 0),
+    };
+    if exp.abs() > MAX_EXPONENT {
+        return None;
+    }
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = Integer::from_str(&format!("{int}{frac}")).ok()?;
+    let scale = exp.checked_sub(i32::try_from(frac.len()).ok()?)?;
+    let pow = Integer::from(Integer::u_pow_u(10, scale.unsigned_abs()));
+
+    Some(match scale < 0 {
+        true => Rational::from((digits, pow)),
+        _ => Rational::from(digits * pow),
+    })
+}
+
+#[allow(unused)]
+impl Ccg {
+    /// Computes the exact weighted model count under `assumptions`.
+    pub fn weighted_count(&self, assumptions: &[i32], weights: &Weights) -> Rational {
+        self.evaluate(&SumProduct, assumptions, |lit, val| {
+            Rational::from(val) * weights.get(lit)
+        })
+    }
+
+    /// Computes the weighted model count under `assumptions` and, for every
+    /// variable `v`, under `assumptions` extended by `v` and by `-v`
+    /// respectively, in one bottom-up and one top-down pass like
+    /// [`Ccg::marginals`].
+    pub fn weighted_marginals(
+        &self,
+        assumptions: &[i32],
+        weights: &Weights,
+    ) -> (Rational, Vec<(Rational, Rational)>) {
+        let vals = self.evaluate_nodes(&SumProduct, assumptions, |lit, val| {
+            Rational::from(val) * weights.get(lit)
+        });
+        let derivs = self.derivatives_in(&SumProduct, &vals);
+        let total = vals.last().cloned().unwrap_or_default();
+
+        let marginals = (0..=self.var_count())
+            .map(|var| {
+                let (mut pos, mut neg) = (total.clone(), total.clone());
+                for leaf in self.leaves_of(var) {
+                    let leaf = *leaf as usize;
+                    let contribution = Rational::from(&derivs[leaf] * &vals[leaf]);
+                    match self.lits[leaf] > 0 {
+                        true => neg -= contribution,
+                        _ => pos -= contribution,
+                    }
+                }
+                (pos, neg)
+            })
+            .collect();
+
+        (total, marginals)
+    }
+
+    /// Computes the weighted model count under `assumptions` with floats of
+    /// precision `prec` bits.
+    pub fn weighted_count_float(&self, assumptions: &[i32], weights: &Weights, prec: u32) -> Float {
+        self.evaluate(&FloatSumProduct { prec }, assumptions, |lit, val| {
+            Float::with_val(prec, weights.get(lit)) * val
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weights_from_decimals() {
+        assert_eq!(parse_weight("0.3"), Some(Rational::from((3, 10))));
+        assert_eq!(parse_weight("2.5e-1"), Some(Rational::from((1, 4))));
+        assert_eq!(parse_weight("1/3"), Some(Rational::from((1, 3))));
+        assert_eq!(parse_weight("4"), Some(Rational::from(4)));
+        assert_eq!(parse_weight("x"), None);
+        assert_eq!(parse_weight("1e999999999"), None);
+    }
+
+    #[test]
+    fn weighted_count_example() -> Result<()> {
+        let graph = Ccg::read("examples/example.lp.as.cnf.nnf.ccg")?;
+        assert_eq!(graph.weighted_count(&[], &Weights::default()), 6);
+
+        let weights = Weights::parse("a 0.5\n~a 0.5\nc p weight 9 1/4 0\n", graph.mapping())?;
+        let total = graph.weighted_count(&[], &weights);
+        let parts = graph.weighted_count(&[10], &weights) + graph.weighted_count(&[-10], &weights);
+        assert_eq!(total, parts);
+        assert_eq!(
+            graph.weighted_count_float(&[], &weights, 64),
+            total.to_f64()
+        );
+
+        let (marginal_total, marginals) = graph.weighted_marginals(&[-9], &weights);
+        assert_eq!(marginal_total, graph.weighted_count(&[-9], &weights));
+        for var in [3, 9, 10, 15] {
+            assert_eq!(
+                marginals[var].0,
+                graph.weighted_count(&[-9, var as i32], &weights)
+            );
+            assert_eq!(
+                marginals[var].1,
+                graph.weighted_count(&[-9, -(var as i32)], &weights)
+            );
+        }
+        Ok(())
+    }
+}
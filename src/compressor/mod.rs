@@ -1,5 +1,5 @@
 use clingo::{Part, SolverLiteral};
-use rug::Integer;
+use rug::{Integer, Rational};
 use std::collections::{HashMap, HashSet};
 use std::fs::read_to_string;
 use std::io::Write;
@@ -7,6 +7,8 @@ use std::path::Path;
 use std::str::FromStr;
 use thiserror::Error;
 
+use crate::ccg::{parse_weight, Ccg, CcgError};
+
 const AND: u8 = 1;
 const OR: u8 = 0;
 
@@ -20,13 +22,50 @@ pub enum CompressorError {
     None,
     #[error("reading data failed")]
     ReadingError(String),
+    #[error("ccg error: {0}")]
+    Ccg(#[from] CcgError),
 }
 
 pub type Result<T> = std::result::Result<T, CompressorError>;
 
 pub fn compress_<S: AsRef<Path>>(nnf_path: S, lp_path: S, cnf_path: S) -> Result<()> {
+    transpile_(nnf_path, lp_path, cnf_path, &mut std::io::stdout().lock())
+}
+
+/// Like [`compress_`], but additionally merges isomorphic nodes and flattens
+/// nested gates of the same type (see [`Ccg::minimize`]).
+pub fn compress_minimized_<S: AsRef<Path>>(nnf_path: S, lp_path: S, cnf_path: S) -> Result<()> {
+    let mut ccg = vec![];
+    transpile_(nnf_path, lp_path, cnf_path, &mut ccg)?;
+    let graph = String::from_utf8(ccg)
+        .map_err(|e| CompressorError::ReadingError(e.to_string()))?
+        .parse::<Ccg>()?;
+
+    Ok(graph.minimize().write_text(&mut std::io::stdout().lock())?)
+}
+
+fn transpile_<S: AsRef<Path>>(
+    nnf_path: S,
+    lp_path: S,
+    cnf_path: S,
+    out: &mut impl Write,
+) -> Result<()> {
     let cnf_mappings = read_cnf_mappings_(cnf_path)?;
     let lp = read_to_string(lp_path)?;
+    let (lp, probabilities) = match lp.contains("::") {
+        true => strip_probabilistic_facts(&lp)?,
+        _ => (lp, vec![]),
+    };
+    let weights = probabilities
+        .iter()
+        .map(|(atom, p)| match cnf_mappings.get(atom) {
+            Some(i) => Ok([(*i, p.clone()), (-i, Rational::from(1 - p))]),
+            _ => Err(CompressorError::ReadingError(format!(
+                "probabilistic fact {atom:?} has no cnf variable."
+            ))),
+        })
+        .collect::<Result<Vec<_>>>()?
+        .concat();
 
     let mut ctl = clingo::control(vec!["0".to_owned()])?;
 
@@ -244,7 +283,7 @@ pub fn compress_<S: AsRef<Path>>(nnf_path: S, lp_path: S, cnf_path: S) -> Result
         count.to_f64().log10()
     );
 
-    write(&stats, &transpilation, &cnf_mappings)
+    write(out, &stats, &transpilation, &cnf_mappings, &weights)
 }
 
 pub fn compress(nnf_path: String) -> Result<()> {
@@ -469,23 +508,146 @@ pub fn compress(nnf_path: String) -> Result<()> {
         count.to_f64().log10()
     );
 
-    write(&stats, &transpilation, &cnf_mappings)
+    write(
+        &mut std::io::stdout().lock(),
+        &stats,
+        &transpilation,
+        &cnf_mappings,
+        &[],
+    )
 }
 
-fn read_cnf_mappings_<S: AsRef<Path>>(cnf_path: S) -> Result<HashMap<String, i32>> {
+/// Replaces probabilistic facts `p::a.` in `lp` by choices `{a}.`.
+///
+/// Returns the stripped program along with the probability of each
+/// annotated atom, whose whitespace is removed to match the atom names in
+/// cnf mappings. Strings, comments and scripts are left untouched.
+pub fn strip_probabilistic_facts(lp: &str) -> Result<(String, Vec<(String, Rational)>)> {
+    let mut stripped = String::with_capacity(lp.len());
+    let mut probabilities = vec![];
+    let mut rest = lp;
+
+    loop {
+        // copy whitespace and comments up to the next statement
+        let start = code(rest)
+            .find(|(_, c)| !c.is_ascii_whitespace())
+            .map_or(rest.len(), |(i, _)| i);
+        stripped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.is_empty() {
+            break;
+        }
+        if rest.starts_with("#script") {
+            let end = rest.find("#end.").map_or(rest.len(), |i| i + "#end.".len());
+            stripped.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+
+        // a probability is a number in decimal, scientific or p/q notation
+        let number = rest
+            .find(|c: char| !(c.is_ascii_digit() || ".eE+-/ \t".contains(c)))
+            .unwrap_or(rest.len());
+        match rest[number..].strip_prefix("::") {
+            Some(fact) if number > 0 => {
+                let p = parse_weight(rest[..number].trim())
+                    .filter(|p| *p >= 0 && *p <= 1)
+                    .ok_or(CompressorError::ReadingError(format!(
+                        "invalid probability in {:?}.",
+                        rest.lines().next().unwrap_or_default().trim()
+                    )))?;
+                let end = statement_end(fact).ok_or(CompressorError::ReadingError(format!(
+                    "probabilistic fact {:?} is not terminated.",
+                    rest.trim()
+                )))?;
+                let atom = &fact[..end];
+                if code(atom).any(|(_, c)| b".:;|".contains(&c)) {
+                    return Err(CompressorError::ReadingError(format!(
+                        "probabilistic statement {:?} is not a ground fact.",
+                        format!("{}.", rest[..number + 2 + end].trim())
+                    )));
+                }
+                probabilities.push((atom.split_whitespace().collect::<String>(), p));
+                stripped.push_str(&format!("{{{}}}.", atom.trim()));
+                rest = &fact[end + 1..];
+            }
+            _ => {
+                let end = statement_end(rest).map_or(rest.len(), |end| end + 1);
+                stripped.push_str(&rest[..end]);
+                rest = &rest[end..];
+            }
+        }
+    }
+
+    Ok((stripped, probabilities))
+}
+
+/// Iterates over the positions and bytes of `s` outside of strings and
+/// comments, including the quotes delimiting strings.
+fn code(s: &str) -> impl Iterator<Item = (usize, u8)> + '_ {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    let mut quoted = false;
+
+    std::iter::from_fn(move || loop {
+        let (at, c) = (i, *bytes.get(i)?);
+        i += 1;
+        match c {
+            b'\\' if quoted => i += 1,
+            b'"' => {
+                quoted = !quoted;
+                return Some((at, c));
+            }
+            b'%' if !quoted => {
+                i = match bytes.get(i) {
+                    Some(b'*') => s[i..].find("*%").map_or(s.len(), |j| i + j + 2),
+                    _ => s[i..].find('\n').map_or(s.len(), |j| i + j),
+                };
+            }
+            _ if quoted => (),
+            _ => return Some((at, c)),
+        }
+    })
+}
+
+/// Returns the position of the dot terminating the statement at the start
+/// of `s`.
+fn statement_end(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut depth = 0;
+
+    code(s)
+        .find(|(i, c)| match c {
+            b'(' => {
+                depth += 1;
+                false
+            }
+            b')' => {
+                depth -= 1;
+                false
+            }
+            b'.' => {
+                let interval = bytes.get(i + 1) == Some(&b'.') || (*i > 0 && bytes[i - 1] == b'.');
+                depth == 0 && !interval
+            }
+            _ => false,
+        })
+        .map(|(i, _)| i)
+}
+
+/// Reads the `c <int> <atom>` lines of cnf `cnf_path` into a mapping from
+/// atoms to variables, skipping other comments.
+pub fn read_cnf_mappings_<S: AsRef<Path>>(cnf_path: S) -> Result<HashMap<String, i32>> {
     let mut mappings: HashMap<String, i32> = HashMap::new();
 
     let cnf = read_to_string(cnf_path)?;
 
-    for mapping in cnf.lines().skip(1).filter(|line| line.starts_with('c')) {
+    // comments other than mappings, e.g., weights, are skipped
+    for mapping in cnf.lines().skip(1).filter(|line| line.starts_with("c ")) {
         let mut line = mapping.split_whitespace().skip(1);
-        let i = line
-            .next()
-            .and_then(|i| i32::from_str(i).ok())
-            .ok_or(CompressorError::None)?;
-        let s = line.next().ok_or(CompressorError::None)?;
-
-        mappings.insert(s.to_owned(), i);
+        if let (Some(Ok(i)), Some(s)) = (line.next().map(i32::from_str), line.next()) {
+            mappings.insert(s.to_owned(), i);
+        }
     }
 
     Ok(mappings)
@@ -513,19 +675,22 @@ fn read_cnf_mappings(filename: &str) -> Result<HashMap<String, i32>> {
 }
 
 fn write(
+    handle: &mut impl Write,
     stats: &str,
     transpilation: &[(usize, &Vec<rug::Integer>)],
     cnf_mappings: &HashMap<String, i32>,
+    weights: &[(i32, Rational)],
 ) -> Result<()> {
-    let stdout = std::io::stdout();
-    let mut handle = stdout.lock();
-
     handle.write_all(format!("{}\n", stats).as_bytes())?;
 
     for (atom, int) in cnf_mappings {
         handle.write_all(format!("c {:?} {}\n", int, atom).as_bytes())?;
     }
 
+    for (lit, weight) in weights {
+        handle.write_all(format!("c p weight {:?} {} 0\n", lit, weight).as_bytes())?;
+    }
+
     for (_, node) in transpilation {
         match node.len() {
             2 => {
@@ -558,3 +723,48 @@ fn write(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skip_comments_besides_mappings() -> Result<()> {
+        let path = std::env::temp_dir().join("iascar_skip_comments_besides_mappings.cnf");
+        std::fs::write(
+            &path,
+            "p cnf 2 1\nc 1 a\nc p weight 1 0.5 0\nc lp2sat\nc 2 b\n1 2 0\n",
+        )?;
+        let mappings = read_cnf_mappings_(&path)?;
+        assert_eq!(
+            mappings,
+            HashMap::from([("a".to_owned(), 1), ("b".to_owned(), 2)])
+        );
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn strip_facts_by_statement() -> Result<()> {
+        let lp = "0.3::a. 1/2 :: b(1).\n% 0.1::c.\nd(\"x::y\").\n%* 0.2::e. *% f :- d(_).\n";
+        let (stripped, probabilities) = strip_probabilistic_facts(lp)?;
+        assert_eq!(
+            stripped,
+            "{a}. {b(1)}.\n% 0.1::c.\nd(\"x::y\").\n%* 0.2::e. *% f :- d(_).\n"
+        );
+        assert_eq!(
+            probabilities,
+            vec![
+                ("a".to_owned(), Rational::from((3, 10))),
+                ("b(1)".to_owned(), Rational::from((1, 2)))
+            ]
+        );
+        assert!(strip_probabilistic_facts("2::a.").is_err());
+        assert!(strip_probabilistic_facts("0.5::a").is_err());
+        assert!(strip_probabilistic_facts("0.3::p(X) :- q(X).").is_err());
+        assert!(strip_probabilistic_facts("0.3::p(1;2).").is_err());
+        assert!(strip_probabilistic_facts("0.3::p(1..2).").is_err());
+        assert!(strip_probabilistic_facts("0.3::p(\"a;b\").").is_ok());
+        Ok(())
+    }
+}
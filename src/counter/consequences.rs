@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+
+use super::Counter;
+
+/// Brave and cautious consequences and facets of the answer sets under some
+/// assumptions, each sorted by predicate signature and atom.
+#[allow(unused)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Consequences {
+    /// Atoms true in some answer set.
+    pub brave: Vec<String>,
+    /// Atoms true in every answer set, i.e., the backbone.
+    pub cautious: Vec<String>,
+    /// Atoms true in some but not all answer sets.
+    pub facets: Vec<String>,
+}
+
+#[allow(unused)]
+impl Counter {
+    /// Returns the consequences of the answer sets under `condition` from a
+    /// single marginal pass, or `None` if there is no answer set.
+    pub fn consequences<S: ToString>(
+        &self,
+        condition: impl Iterator<Item = S>,
+    ) -> Option<Consequences> {
+        let (count, marginals) = self.graph.marginals(&self.read_assumptions(condition));
+        if count == 0 {
+            return None;
+        }
+
+        let mut consequences = Consequences::default();
+        for (atom, var) in self.graph.mapping() {
+            let Some((pos, neg)) = marginals.get(*var as usize) else {
+                continue;
+            };
+            if *pos > 0 {
+                consequences.brave.push(atom.clone());
+            }
+            match (*pos > 0, *neg > 0) {
+                (true, true) => consequences.facets.push(atom.clone()),
+                (true, false) => consequences.cautious.push(atom.clone()),
+                _ => (),
+            }
+        }
+        for atoms in [
+            &mut consequences.brave,
+            &mut consequences.cautious,
+            &mut consequences.facets,
+        ] {
+            atoms.sort_by(|a, b| (signature(a), a).cmp(&(signature(b), b)));
+        }
+
+        Some(consequences)
+    }
+
+    /// Returns the atoms true in some answer set under `condition`.
+    pub fn brave<S: ToString>(&self, condition: impl Iterator<Item = S>) -> Vec<String> {
+        self.consequences(condition)
+            .map(|c| c.brave)
+            .unwrap_or_default()
+    }
+
+    /// Returns the atoms true in every answer set under `condition`, or
+    /// `None` if there is no answer set.
+    pub fn cautious<S: ToString>(&self, condition: impl Iterator<Item = S>) -> Option<Vec<String>> {
+        self.consequences(condition).map(|c| c.cautious)
+    }
+
+    /// Returns the facets under `condition`, i.e., the atoms true in some but
+    /// not all answer sets.
+    pub fn facets<S: ToString>(&self, condition: impl Iterator<Item = S>) -> Vec<String> {
+        self.consequences(condition)
+            .map(|c| c.facets)
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the predicate signature `name/arity` of a ground atom.
+pub fn signature(atom: &str) -> String {
+    let atom = atom.trim();
+    match atom.find('(') {
+        Some(i) => {
+            let mut depth = 0;
+            let arity = atom[i..]
+                .chars()
+                .filter(|c| {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => (),
+                    }
+                    *c == ',' && depth == 1
+                })
+                .count()
+                + 1;
+            format!("{}/{}", &atom[..i], arity)
+        }
+        _ => format!("{}/0", atom),
+    }
+}
+
+/// Groups `atoms` by their predicate signature.
+pub fn by_signature(atoms: &[String]) -> BTreeMap<String, Vec<&str>> {
+    let mut groups: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for atom in atoms {
+        groups.entry(signature(atom)).or_default().push(atom);
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Result;
+
+    #[test]
+    fn consequences_match_models() -> Result<()> {
+        let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
+        for condition in [vec![], vec!["~a"], vec!["b", "~i"]] {
+            let models = counter.models(condition.iter(), false).collect::<Vec<_>>();
+            let consequences = counter.consequences(condition.iter()).expect("satisfiable");
+            for atom in counter.atoms() {
+                let n = models
+                    .iter()
+                    .filter(|m| m.iter().any(|a| a == atom))
+                    .count();
+                let has = |atoms: &[String]| atoms.iter().any(|a| a == atom);
+                assert_eq!(has(&consequences.brave), n > 0);
+                assert_eq!(has(&consequences.cautious), n == models.len());
+                assert_eq!(has(&consequences.facets), n > 0 && n < models.len());
+            }
+        }
+        assert_eq!(counter.cautious(["a", "~a"].iter()), None);
+        assert!(counter.facets(["a"].iter()).iter().all(|a| a != "a"));
+        Ok(())
+    }
+
+    #[test]
+    fn signatures() {
+        assert_eq!(signature("a"), "a/0");
+        assert_eq!(signature("p(1,f(2,3))"), "p/2");
+        let atoms = ["q(1)", "p", "q(2)"].map(String::from);
+        let groups = by_signature(&atoms);
+        assert_eq!(groups["q/1"], vec!["q(1)", "q(2)"]);
+        assert_eq!(groups["p/0"], vec!["p"]);
+    }
+}
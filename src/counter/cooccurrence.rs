@@ -0,0 +1,174 @@
+#[cfg(not(feature = "seq"))]
+use rayon::prelude::*;
+use rug::{Integer, Rational};
+use std::io::Write;
+
+use super::{signature, Counter, CounterError, Result};
+
+/// Joint answer set counts of all pairs of some atoms under a condition.
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cooccurrence {
+    pub atoms: Vec<String>,
+    /// Number of answer sets under the condition.
+    pub count: Integer,
+    /// Number of answer sets containing each atom.
+    pub marginals: Vec<Integer>,
+    /// Number of answer sets containing both atoms of each pair.
+    pub joint: Vec<Vec<Integer>>,
+}
+
+#[allow(unused)]
+impl Cooccurrence {
+    /// Returns the probability of atom `j` given atom `i`.
+    pub fn conditional(&self, i: usize, j: usize) -> Option<f64> {
+        (self.marginals[i] > 0).then(|| ratio(&self.joint[i][j], &self.marginals[i]))
+    }
+
+    /// Returns the ratio of the joint probability of atoms `i` and `j` to the
+    /// product of their probabilities.
+    pub fn lift(&self, i: usize, j: usize) -> Option<f64> {
+        let expected = Integer::from(&self.marginals[i] * &self.marginals[j]);
+        (expected > 0).then(|| ratio(&Integer::from(&self.joint[i][j] * &self.count), &expected))
+    }
+
+    /// Returns the pointwise mutual information of atoms `i` and `j` in bits.
+    pub fn pmi(&self, i: usize, j: usize) -> Option<f64> {
+        self.lift(i, j).map(f64::log2)
+    }
+
+    /// Writes a CSV line for every ordered pair of distinct atoms.
+    pub fn write_csv(&self, out: &mut impl Write) -> std::io::Result<()> {
+        writeln!(out, "a,b,count_a,count_b,count_ab,p_b_given_a,lift,pmi")?;
+        let show = |x: Option<f64>| x.map(|x| x.to_string()).unwrap_or_default();
+        for (i, a) in self.atoms.iter().enumerate() {
+            for (j, b) in self.atoms.iter().enumerate().filter(|(j, _)| *j != i) {
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{}",
+                    csv_field(a),
+                    csv_field(b),
+                    self.marginals[i],
+                    self.marginals[j],
+                    self.joint[i][j],
+                    show(self.conditional(i, j)),
+                    show(self.lift(i, j)),
+                    show(self.pmi(i, j)),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[allow(unused)]
+impl Counter {
+    /// Returns the joint counts of all pairs of `atoms` under `condition`.
+    ///
+    /// The counts of all pairs involving an atom `a` are the marginals under
+    /// `condition` and `a`, so one marginal pass per atom, run in parallel,
+    /// replaces recounting every pair.
+    pub fn cooccurrence<S: ToString>(
+        &self,
+        atoms: &[String],
+        condition: impl Iterator<Item = S>,
+    ) -> Result<Cooccurrence> {
+        let vars = atoms
+            .iter()
+            .map(|atom| {
+                self.graph
+                    .mapping()
+                    .get(atom)
+                    .map(|var| *var as usize)
+                    .ok_or_else(|| CounterError::ReadingError(format!("unknown atom {:?}", atom)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let assumptions = self.read_assumptions(condition);
+
+        let row = |var: &usize| {
+            let mut under = assumptions.clone();
+            under.push(*var as i32);
+            let (_, marginals) = self.graph.marginals(&under);
+            vars.iter()
+                .map(|v| marginals.get(*v).map_or_else(Integer::new, |m| m.0.clone()))
+                .collect::<Vec<_>>()
+        };
+        #[cfg(not(feature = "seq"))]
+        let joint = vars.par_iter().map(row).collect::<Vec<_>>();
+        #[cfg(feature = "seq")]
+        let joint = vars.iter().map(row).collect::<Vec<_>>();
+
+        Ok(Cooccurrence {
+            atoms: atoms.to_vec(),
+            count: self.graph.count(&assumptions),
+            marginals: (0..vars.len()).map(|i| joint[i][i].clone()).collect(),
+            joint,
+        })
+    }
+
+    /// Returns the atoms of `predicate` given by its signature, e.g., `p/2`,
+    /// ordered by name.
+    pub fn atoms_of(&self, predicate: &str) -> Vec<String> {
+        let mut atoms = self
+            .atoms()
+            .filter(|atom| signature(atom) == predicate)
+            .map(|atom| atom.to_owned())
+            .collect::<Vec<_>>();
+        atoms.sort();
+        atoms
+    }
+}
+
+fn ratio(numerator: &Integer, denominator: &Integer) -> f64 {
+    Rational::from((numerator, denominator)).to_f64()
+}
+
+/// Quotes `field` if it contains a separator or quote.
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        _ => field.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cooccurrence_matches_counts() -> Result<()> {
+        let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
+        let atoms = counter.atoms_of("b/0");
+        assert_eq!(atoms, vec!["b"]);
+
+        let atoms = ["a", "b", "c", "h", "e"].map(String::from);
+        let matrix = counter.cooccurrence(&atoms, ["~i"].iter())?;
+        assert_eq!(matrix.count, counter.count(["~i"].iter()));
+        for (i, a) in atoms.iter().enumerate() {
+            assert_eq!(matrix.marginals[i], counter.count(["~i", a].iter()));
+            for (j, b) in atoms.iter().enumerate() {
+                assert_eq!(matrix.joint[i][j], counter.count(["~i", a, b].iter()));
+            }
+        }
+        // h is true in every answer set without i, so it is independent
+        assert_eq!(matrix.lift(0, 3), Some(1.0));
+        assert_eq!(matrix.pmi(0, 3), Some(0.0));
+        assert_eq!(matrix.conditional(1, 2), Some(0.5));
+        assert_eq!(matrix.pmi(0, 1), Some(f64::NEG_INFINITY));
+
+        let mut csv = vec![];
+        matrix
+            .write_csv(&mut csv)
+            .map_err(|e| CounterError::ReadingError(e.to_string()))?;
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 1 + 5 * 4);
+        assert!(csv.contains("\na,h,1,3,1,1,1,0\n"));
+
+        assert!(counter
+            .cooccurrence(&["x".to_owned()], ["~i"].iter())
+            .is_err());
+        assert_eq!(csv_field("p(1,2)"), "\"p(1,2)\"");
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+
+use super::{Counter, IncrementalCounter};
+use crate::ccg::literal_names;
+
+#[allow(unused)]
+impl Counter {
+    /// Returns a minimal subset of `condition` without answer sets, as `a` or
+    /// `~a`, or `None` if there is an answer set under `condition`.
+    ///
+    /// Deletion-based: every assumption is retracted in turn and kept out if
+    /// the rest still has no answer set, using the incremental evaluator.
+    pub fn explain<S: ToString>(&self, condition: impl Iterator<Item = S>) -> Option<Vec<String>> {
+        let mut evaluator = self.incremental();
+        let mus = self.shrink(&mut evaluator, &self.distinct_assumptions(condition))?;
+
+        Some(literal_names(&mus, self.mapping()))
+    }
+
+    /// Returns all minimal subsets of `condition` without answer sets.
+    ///
+    /// Any other minimal subset misses some member of one found, so it is
+    /// found among the assumptions without that member, which are searched
+    /// in turn for every member.
+    pub fn explain_all<S: ToString>(&self, condition: impl Iterator<Item = S>) -> Vec<Vec<String>> {
+        let mut evaluator = self.incremental();
+        let mut found: Vec<Vec<i32>> = vec![];
+        let mut visited = HashSet::new();
+        let mut stack = vec![self.distinct_assumptions(condition)];
+
+        while let Some(assumptions) = stack.pop() {
+            if !visited.insert(assumptions.clone()) {
+                continue;
+            }
+            // reuse a subset found before if possible
+            let mus = match found
+                .iter()
+                .find(|m| m.iter().all(|l| assumptions.contains(l)))
+            {
+                Some(mus) => mus.clone(),
+                _ => match self.shrink(&mut evaluator, &assumptions) {
+                    Some(mus) => {
+                        found.push(mus.clone());
+                        mus
+                    }
+                    _ => continue,
+                },
+            };
+            for lit in mus {
+                stack.push(assumptions.iter().copied().filter(|l| *l != lit).collect());
+            }
+        }
+
+        found
+            .iter()
+            .map(|mus| literal_names(mus, self.mapping()))
+            .collect()
+    }
+
+    /// Shrinks `assumptions` to a minimal subset without models, or returns
+    /// `None` if there is a model under `assumptions`.
+    fn shrink(
+        &self,
+        evaluator: &mut IncrementalCounter<'_>,
+        assumptions: &[i32],
+    ) -> Option<Vec<i32>> {
+        while evaluator.pop_assumption().is_some() {}
+        assumptions
+            .iter()
+            .for_each(|lit| evaluator.push_literal(*lit));
+        if evaluator.count() > 0 {
+            return None;
+        }
+
+        for lit in assumptions {
+            evaluator.retract_literal(*lit);
+            if evaluator.count() > 0 {
+                evaluator.push_literal(*lit);
+            }
+        }
+        let mut mus = evaluator.assumptions().to_vec();
+        mus.sort_by_key(|l| assumptions.iter().position(|a| a == l));
+
+        Some(mus)
+    }
+
+    /// Reads `condition`, dropping repeated assumptions.
+    fn distinct_assumptions<S: ToString>(&self, condition: impl Iterator<Item = S>) -> Vec<i32> {
+        let mut assumptions = self.read_assumptions(condition);
+        let mut seen = HashSet::new();
+        assumptions.retain(|lit| seen.insert(*lit));
+        assumptions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Result;
+
+    #[test]
+    fn minimal_unsatisfiable_subsets() -> Result<()> {
+        let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
+        assert_eq!(counter.explain(["a", "e"].iter()), None);
+
+        let condition = ["e", "a", "b", "h", "~g"];
+        let mus = counter.explain(condition.iter()).expect("unsatisfiable");
+        assert_eq!(counter.count(mus.iter()), 0);
+        for i in 0..mus.len() {
+            let mut rest = mus.clone();
+            rest.remove(i);
+            assert!(counter.count(rest.iter()) > 0);
+        }
+
+        let mut all = counter.explain_all(condition.iter());
+        all.iter_mut().for_each(|m| m.sort());
+        all.sort();
+        assert_eq!(all, vec![vec!["a", "b"], vec!["h", "~g"]]);
+        assert!(counter.explain_all(["a"].iter()).is_empty());
+        Ok(())
+    }
+}
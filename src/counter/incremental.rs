@@ -0,0 +1,217 @@
+use rug::{Assign, Integer};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::Counter;
+use crate::ccg::{AND, LIT};
+
+/// Stateful evaluator that keeps node values of a [`Counter`] under a stack
+/// of assumptions.
+///
+/// Pushing or popping an assumption only re-evaluates the ancestors of the
+/// leaves it falsifies, stopping at nodes whose value did not change.
+#[allow(unused)]
+#[derive(Debug, Clone)]
+pub struct IncrementalCounter<'a> {
+    counter: &'a Counter,
+    vals: Vec<Integer>,
+    parent_offsets: Vec<u32>,
+    parents: Vec<u32>,
+    blocked: Vec<u32>,
+    queued: Vec<bool>,
+    assumptions: Vec<i32>,
+    scratch: Integer,
+}
+
+#[allow(unused)]
+impl<'a> IncrementalCounter<'a> {
+    pub fn new(counter: &'a Counter) -> Self {
+        let graph = &counter.graph;
+        let n = graph.node_count();
+
+        let mut parent_offsets = vec![0u32; n + 1];
+        (0..n)
+            .flat_map(|node| graph.children(node))
+            .for_each(|child| parent_offsets[*child as usize + 1] += 1);
+        for i in 0..n {
+            parent_offsets[i + 1] += parent_offsets[i];
+        }
+        let mut fill = parent_offsets.clone();
+        let mut parents = vec![0u32; graph.edge_count()];
+        for node in 0..n {
+            for child in graph.children(node) {
+                parents[fill[*child as usize] as usize] = node as u32;
+                fill[*child as usize] += 1;
+            }
+        }
+
+        let mut vals = vec![];
+        graph.count_with(&[], &mut vals);
+
+        Self {
+            counter,
+            vals,
+            parent_offsets,
+            parents,
+            blocked: vec![0; n],
+            queued: vec![false; n],
+            assumptions: vec![],
+            scratch: Integer::new(),
+        }
+    }
+
+    /// Returns count under the current assumptions.
+    pub fn count(&self) -> Integer {
+        self.vals.last().cloned().unwrap_or_default()
+    }
+
+    /// Returns the current assumptions in the order they were pushed.
+    pub fn assumptions(&self) -> &[i32] {
+        &self.assumptions
+    }
+
+    /// Pushes assumption given as `a` or `~a`; unknown atoms are ignored.
+    pub fn push_assumption<S: ToString>(&mut self, assumption: S) -> bool {
+        match self.counter.read_assumption(assumption.to_string()) {
+            Some(lit) => {
+                self.push_literal(lit);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Retracts the most recent occurrence of assumption given as `a` or
+    /// `~a`, returning whether it was assumed.
+    pub fn retract_assumption<S: ToString>(&mut self, assumption: S) -> bool {
+        match self.counter.read_assumption(assumption.to_string()) {
+            Some(lit) => self.retract_literal(lit),
+            _ => false,
+        }
+    }
+
+    /// Pushes assumption given as cnf literal.
+    pub fn push_literal(&mut self, lit: i32) {
+        self.assumptions.push(lit);
+        self.update_leaves(lit, true);
+    }
+
+    /// Pops the most recent assumption.
+    pub fn pop_assumption(&mut self) -> Option<i32> {
+        let lit = self.assumptions.pop()?;
+        self.update_leaves(lit, false);
+
+        Some(lit)
+    }
+
+    /// Retracts the most recent occurrence of `lit`, wherever it is on the
+    /// assumption stack.
+    pub fn retract_literal(&mut self, lit: i32) -> bool {
+        match self.assumptions.iter().rposition(|l| *l == lit) {
+            Some(i) => {
+                self.assumptions.remove(i);
+                self.update_leaves(lit, false);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update_leaves(&mut self, lit: i32, block: bool) {
+        let graph = &self.counter.graph;
+        let mut queue = BinaryHeap::new();
+        for leaf in graph.leaves_of(lit.unsigned_abs() as usize) {
+            let leaf = *leaf as usize;
+            if graph.literal(leaf) == -lit {
+                match block {
+                    true => self.blocked[leaf] += 1,
+                    _ => self.blocked[leaf] -= 1,
+                }
+                if !self.queued[leaf] {
+                    self.queued[leaf] = true;
+                    queue.push(Reverse(leaf));
+                }
+            }
+        }
+
+        while let Some(Reverse(node)) = queue.pop() {
+            self.queued[node] = false;
+            if self.update(node) {
+                for parent in self.parent_offsets[node]..self.parent_offsets[node + 1] {
+                    let parent = self.parents[parent as usize] as usize;
+                    if !self.queued[parent] {
+                        self.queued[parent] = true;
+                        queue.push(Reverse(parent));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Re-evaluates `node` from its children and returns whether its value
+    /// changed.
+    fn update(&mut self, node: usize) -> bool {
+        let graph = &self.counter.graph;
+        match graph.kind(node) {
+            LIT => match self.blocked[node] > 0 {
+                true => self.scratch.assign(0),
+                _ => self.scratch.assign(graph.value(node)),
+            },
+            AND => {
+                self.scratch.assign(1);
+                for child in graph.children(node) {
+                    self.scratch *= &self.vals[*child as usize];
+                }
+            }
+            _ => {
+                self.scratch.assign(0);
+                for child in graph.children(node) {
+                    self.scratch += &self.vals[*child as usize];
+                }
+            }
+        }
+
+        match self.scratch == self.vals[node] {
+            true => false,
+            _ => {
+                std::mem::swap(&mut self.vals[node], &mut self.scratch);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Result;
+
+    #[test]
+    fn push_pop_matches_count() -> Result<()> {
+        let counter = Counter::new("examples/example.lp.sm.cnf.nnf.ccg")?;
+        let mut inc = IncrementalCounter::new(&counter);
+        assert_eq!(inc.count(), counter.count(Vec::<String>::new().iter()));
+
+        let mut assumed = vec![];
+        for a in ["~a", "f", "~i", "c"] {
+            assert!(inc.push_assumption(a));
+            assumed.push(a);
+            assert_eq!(inc.count(), counter.count(assumed.iter()));
+        }
+        assert!(!inc.push_assumption("nope"));
+
+        assert!(inc.retract_literal(-10));
+        assumed.remove(0);
+        assert_eq!(inc.count(), counter.count(assumed.iter()));
+        assert!(inc.retract_assumption("~i"));
+        assert!(!inc.retract_assumption("~i"));
+        assumed.retain(|a| *a != "~i");
+        assert_eq!(inc.count(), counter.count(assumed.iter()));
+
+        while inc.pop_assumption().is_some() {
+            assumed.pop();
+            assert_eq!(inc.count(), counter.count(assumed.iter()));
+        }
+        Ok(())
+    }
+}
@@ -1,11 +1,33 @@
+mod consequences;
+mod cooccurrence;
+mod explain;
+mod incremental;
+mod query;
+mod relations;
+mod session;
+mod suggest;
+
+#[cfg(not(feature = "seq"))]
+use rayon::prelude::*;
 #[allow(unused)]
-use rug::Integer;
+use rug::{rand::RandState, Float, Integer, Rational};
 use std::collections::{HashMap, HashSet};
-use std::fs::read_to_string;
 use std::path::Path;
-use std::str::FromStr;
 
-use crate::{SAND, SOR};
+use crate::ccg::{
+    parse_constraints, read_literal, Ccg, MaxProduct, MinSum, Models, Sampler, Semiring, Weights,
+};
+#[allow(unused)]
+pub use consequences::{by_signature, signature, Consequences};
+#[allow(unused)]
+pub use cooccurrence::Cooccurrence;
+pub use incremental::IncrementalCounter;
+#[allow(unused)]
+pub use query::Query;
+#[allow(unused)]
+pub use relations::Relations;
+#[allow(unused)]
+pub use session::NavigationSession;
 
 #[allow(unused)]
 #[derive(Debug, Clone)]
@@ -16,147 +38,396 @@ pub enum CounterError {
 #[allow(unused)]
 pub type Result<T> = std::result::Result<T, CounterError>;
 
+/// Largest number of answer sets drawn by one `sample` command of the
+/// server or the interactive mode.
+pub const MAX_SAMPLES: usize = 100_000;
+
 #[allow(unused)]
 #[derive(Debug, Clone)]
 pub struct Counter {
-    counting_graph: Vec<String>,
-    mapping: HashMap<String, i32>,
-    node_count: usize,
-    overall_count: f64,
+    graph: Ccg,
+    symbols: HashMap<i32, String>,
 }
 #[allow(unused)]
 impl Counter {
     pub fn new(ccg_filename: impl AsRef<Path>) -> Result<Self> {
-        let dag = read_to_string(&ccg_filename)
+        let graph =
+            Ccg::read(&ccg_filename).map_err(|err| CounterError::ReadingError(err.to_string()))?;
+
+        Ok(Self {
+            symbols: graph
+                .mapping()
+                .iter()
+                .map(|(atom, var)| (*var, atom.clone()))
+                .collect(),
+            graph,
+        })
+    }
+
+    pub fn count<S: ToString>(&self, assume: impl Iterator<Item = S>) -> Integer {
+        self.graph.count(&self.read_assumptions(assume))
+    }
+
+    /// Returns the number of answer sets under each assumption set of
+    /// `batch`, in order, evaluated in parallel.
+    pub fn count_batch(&self, batch: &[Vec<String>]) -> Vec<Integer> {
+        let count = |vals: &mut Vec<Integer>, assume: &Vec<String>| {
+            self.graph
+                .count_with(&self.read_assumptions(assume.iter()), vals)
+        };
+        #[cfg(not(feature = "seq"))]
+        let counts = batch.par_iter().map_init(Vec::new, count).collect();
+        #[cfg(feature = "seq")]
+        let counts = {
+            let mut vals = vec![];
+            batch
+                .iter()
+                .map(|assume| count(&mut vals, assume))
+                .collect()
+        };
+
+        counts
+    }
+
+    /// Returns the number of answer sets under `assume` satisfying the Boolean
+    /// formula `query` over atom names, see [`Query`].
+    pub fn count_query<S: ToString>(
+        &self,
+        query: &str,
+        assume: impl Iterator<Item = S>,
+    ) -> Result<Integer> {
+        let terms = query
+            .parse::<Query>()?
+            .dnf(&|atom| self.read_assumption(atom.to_owned()))?;
+        Ok(self.graph.count_dnf(&terms, &self.read_assumptions(assume)))
+    }
+
+    /// Returns the number of answer sets under `assume` that satisfy the
+    /// ground integrity constraints `constraints`, e.g., `:- a, not b.`.
+    ///
+    /// The count is exact for `depth` 0 and otherwise obtained by
+    /// inclusion–exclusion stopped after `depth` alternations.
+    pub fn count_denying<S: ToString>(
+        &self,
+        constraints: &str,
+        assume: impl Iterator<Item = S>,
+        depth: usize,
+    ) -> Result<Integer> {
+        let denied = parse_constraints(constraints, self.graph.mapping())
             .map_err(|err| CounterError::ReadingError(err.to_string()))?;
+        let assumptions = self.read_assumptions(assume);
+        Ok(match depth {
+            0 => self.graph.count_denying(&denied, &assumptions),
+            _ => self
+                .graph
+                .count_denying_to_depth(&denied, &assumptions, depth),
+        })
+    }
+
+    /// Like [`Counter::count_denying`], but passes the bound after every
+    /// alternation to `bound`, which stops the evaluation by returning
+    /// `false`.
+    pub fn count_denying_anytime<S: ToString>(
+        &self,
+        constraints: &str,
+        assume: impl Iterator<Item = S>,
+        depth: usize,
+        bound: impl FnMut(usize, &Integer) -> bool,
+    ) -> Result<Integer> {
+        let denied = parse_constraints(constraints, self.graph.mapping())
+            .map_err(|err| CounterError::ReadingError(err.to_string()))?;
+        Ok(self
+            .graph
+            .count_denying_anytime(&denied, &self.read_assumptions(assume), depth, bound))
+    }
+
+    /// Reads literal weights from `weights`, resolving atom names.
+    pub fn read_weights(&self, weights: impl AsRef<Path>) -> Result<Weights> {
+        Weights::read(weights, self.graph.mapping())
+            .map_err(|err| CounterError::ReadingError(err.to_string()))
+    }
 
-        let mut lines = dag.lines();
-        let (overall_count, node_count) = lines
-            .next()
-            .and_then(|line| {
-                let mut ls = line.split_whitespace();
-                ls.nth(1).zip(ls.last())
+    /// Returns exact weighted model count under `assume`.
+    pub fn weighted_count<S: ToString>(
+        &self,
+        assume: impl Iterator<Item = S>,
+        weights: &Weights,
+    ) -> Rational {
+        self.graph
+            .weighted_count(&self.read_assumptions(assume), weights)
+    }
+
+    /// Returns weighted model count under `assume` using floats with `prec`
+    /// bits of precision.
+    pub fn weighted_count_float<S: ToString>(
+        &self,
+        assume: impl Iterator<Item = S>,
+        weights: &Weights,
+        prec: u32,
+    ) -> Float {
+        self.graph
+            .weighted_count_float(&self.read_assumptions(assume), weights, prec)
+    }
+
+    /// Returns literal weights recorded in the graph, e.g. probabilities of
+    /// probabilistic facts.
+    pub fn weights(&self) -> &Weights {
+        self.graph.weights()
+    }
+
+    /// Returns up to `k` answer sets under `condition` with maximal product
+    /// of literal `weights`, best first, as weight and true atoms.
+    pub fn most_probable<S: ToString>(
+        &self,
+        condition: impl Iterator<Item = S>,
+        weights: &Weights,
+        k: usize,
+    ) -> Vec<(Rational, Vec<String>)> {
+        let assumptions = self.read_assumptions(condition);
+        self.graph
+            .best_models(
+                &MaxProduct,
+                &assumptions,
+                |lit, _| weights.get(lit).clone(),
+                k,
+            )
+            .into_iter()
+            .map(|(weight, model)| (weight, self.true_atoms(&model)))
+            .collect()
+    }
+
+    /// Returns up to `k` answer sets under `condition` with minimal sum of
+    /// literal `costs`, best first, as cost and true atoms. Literals without
+    /// cost cost nothing.
+    pub fn min_cost<S: ToString>(
+        &self,
+        condition: impl Iterator<Item = S>,
+        costs: &Weights,
+        k: usize,
+    ) -> Vec<(Rational, Vec<String>)> {
+        let assumptions = self.read_assumptions(condition);
+        let cost = |lit, _| Some(costs.weight(lit).cloned().unwrap_or_default());
+        self.graph
+            .best_models(&MinSum, &assumptions, cost, k)
+            .into_iter()
+            .filter_map(|(cost, model)| cost.map(|c| (c, self.true_atoms(&model))))
+            .collect()
+    }
+
+    /// Draws `n` independent answer sets under `condition` as true atoms,
+    /// uniformly at random or, given literal `weights`, with probability
+    /// proportional to their weight. Returns no answer sets if there is none.
+    pub fn sample<S: ToString>(
+        &self,
+        condition: impl Iterator<Item = S>,
+        n: usize,
+        weights: Option<&Weights>,
+        rng: &mut RandState,
+    ) -> Vec<Vec<String>> {
+        self.sample_until(condition, n, weights, rng, || false)
+    }
+
+    /// Like [`Counter::sample`], but stops drawing once `cancelled` holds,
+    /// returning the answer sets drawn so far.
+    pub fn sample_until<S: ToString>(
+        &self,
+        condition: impl Iterator<Item = S>,
+        n: usize,
+        weights: Option<&Weights>,
+        rng: &mut RandState,
+        cancelled: impl Fn() -> bool,
+    ) -> Vec<Vec<String>> {
+        let assumptions = self.read_assumptions(condition);
+        let sampler = match weights {
+            Some(weights) => Sampler::weighted(&self.graph, &assumptions, weights),
+            _ => Sampler::uniform(&self.graph, &assumptions),
+        };
+
+        (0..n)
+            .map_while(|_| match cancelled() {
+                true => None,
+                _ => sampler.sample(rng),
             })
-            .and_then(|(nc, oc)| f64::from_str(oc).ok().zip(usize::from_str(nc).ok()))
-            .ok_or(CounterError::ReadingError(
-                "could not read node count.".to_owned(),
-            ))?;
-
-        let mut mapping = HashMap::new();
-        let mut atom_count = 0;
-        for l in lines.take_while(|line| line.starts_with("c ")) {
-            let mut iter = l.split_whitespace();
-            iter.next();
-            let v = iter.next().and_then(|s| i32::from_str(s).ok()).ok_or(
-                CounterError::ReadingError("could not read integer in mapping.".to_owned()),
-            )?;
-            let k = iter.next().ok_or(CounterError::ReadingError(
-                "could not read atom in mapping.".to_owned(),
-            ))?;
-            mapping.insert(k.to_owned(), v);
-            atom_count += 1;
-        }
+            .map(|model| self.true_atoms(&model))
+            .collect()
+    }
 
-        let mut counting_graph = Vec::with_capacity(node_count);
-        dag.lines()
-            .skip(atom_count + 1)
-            .for_each(|node| counting_graph.push(node.to_owned()));
+    /// Returns a lazy iterator over the answer sets under `condition` as true
+    /// atoms. Auxiliary variables without atom in the mapping, e.g. those
+    /// introduced by lp2sat, are hidden unless `auxiliary` is set, in which
+    /// case true ones are listed by their integer.
+    pub fn models<S: ToString>(
+        &self,
+        condition: impl Iterator<Item = S>,
+        auxiliary: bool,
+    ) -> impl Iterator<Item = Vec<String>> + '_ {
+        self.named(
+            self.graph.models(&self.read_assumptions(condition)),
+            auxiliary,
+        )
+    }
 
-        Ok(Self {
-            counting_graph,
-            mapping,
-            node_count,
-            overall_count,
-        })
+    /// Like [`Counter::models`], but starts at the answer set with (0-based)
+    /// `index` without enumerating the preceding ones.
+    pub fn models_from<S: ToString>(
+        &self,
+        index: &Integer,
+        condition: impl Iterator<Item = S>,
+        auxiliary: bool,
+    ) -> impl Iterator<Item = Vec<String>> + '_ {
+        let assumptions = self.read_assumptions(condition);
+        self.named(
+            Models::starting_at(&self.graph, &assumptions, index),
+            auxiliary,
+        )
     }
 
-    pub fn count<S: ToString>(&self, assume: impl Iterator<Item = S>) -> Integer {
-        let mut count = Integer::from(0);
+    /// Returns the answer set with (0-based) `index` in the order of
+    /// [`Counter::models`] under `condition` as true atoms.
+    pub fn model_at<S: ToString>(
+        &self,
+        index: &Integer,
+        condition: impl Iterator<Item = S>,
+    ) -> Option<Vec<String>> {
+        self.models_from(index, condition, false).next()
+    }
 
-        let assumptions = assume
-            .map(|s| self.read_assumption(s.to_string()))
-            .flatten()
+    /// Returns the index of the answer set with true atoms `model` in the
+    /// order of [`Counter::models`] under `condition`, or `None` if it is no
+    /// answer set under `condition`.
+    pub fn rank<S: ToString, T: ToString>(
+        &self,
+        model: impl Iterator<Item = S>,
+        condition: impl Iterator<Item = T>,
+    ) -> Option<Integer> {
+        let true_atoms = model.map(|a| a.to_string()).collect::<HashSet<_>>();
+        let model = self
+            .graph
+            .mapping()
+            .iter()
+            .map(|(atom, var)| match true_atoms.contains(atom) {
+                true => *var,
+                _ => -var,
+            })
             .collect::<Vec<_>>();
 
-        let mut nodes = Vec::with_capacity(self.node_count);
-
-        self.counting_graph.iter().for_each(|node| {
-            let mut spec = node.split_whitespace();
-            match spec.next() {
-                Some(SAND) => {
-                    let n_children = spec
-                        .next()
-                        .and_then(|child| usize::from_str(child).ok())
-                        .unwrap();
-
-                    let children_ = &spec
-                        .flat_map(|child| usize::from_str(child).ok())
-                        .collect::<Vec<_>>()[..n_children];
-                    let children = children_
-                        .iter()
-                        .map(|idx| unsafe { nodes.get_unchecked(*idx) })
-                        .collect::<Vec<_>>();
-
-                    count = children
-                        .iter()
-                        .fold(Integer::from(1), |acc, child_val: &&Integer| {
-                            acc * &(*child_val).clone()
-                        });
-
-                    nodes.push(count.clone());
-                }
-                Some(SOR) => {
-                    count = Integer::from(0);
-                    let n_children = spec
-                        .next()
-                        .and_then(|child| usize::from_str(child).ok())
-                        .unwrap();
-
-                    let children_ = &spec
-                        .flat_map(|child| usize::from_str(child).ok())
-                        .collect::<Vec<_>>()[..n_children];
-                    let children = children_
-                        .iter()
-                        .map(|idx| unsafe { nodes.get_unchecked(*idx) })
-                        .collect::<Vec<_>>();
-
-                    children.iter().for_each(|child_val| {
-                        count += &(*child_val).clone();
-                    });
-
-                    nodes.push(count.clone());
-                }
-                o => {
-                    let lit = o
-                        .and_then(|l| i32::from_str(l).ok())
-                        .expect("reading literal failed.");
-
-                    count = spec
-                        .next()
-                        .and_then(|l| i32::from_str(l).map(Integer::from).ok())
-                        .expect("reading val failed.");
-
-                    match assumptions.contains(&-lit) {
-                        false => {
-                            nodes.push(count.clone());
-                        }
-                        _ => {
-                            count = Integer::from(0);
-                            nodes.push(count.clone());
-                        }
-                    }
-                }
-            }
-        });
+        self.graph.rank(&model, &self.read_assumptions(condition))
+    }
+
+    fn named<'a>(
+        &'a self,
+        models: impl Iterator<Item = Vec<i32>> + 'a,
+        auxiliary: bool,
+    ) -> impl Iterator<Item = Vec<String>> + 'a {
+        models.map(move |model| match auxiliary {
+            true => model
+                .iter()
+                .filter(|lit| **lit > 0)
+                .map(|lit| match self.symbols.get(lit) {
+                    Some(atom) => atom.clone(),
+                    _ => lit.to_string(),
+                })
+                .collect(),
+            _ => self.true_atoms(&model),
+        })
+    }
+
+    /// Evaluates the graph in `semiring` under `condition`, where
+    /// `label(lit, val)` gives the value of each literal leaf.
+    pub fn evaluate<R: Semiring, S: ToString>(
+        &self,
+        semiring: &R,
+        condition: impl Iterator<Item = S>,
+        label: impl Fn(i32, i32) -> R::Element,
+    ) -> R::Element {
+        self.graph
+            .evaluate(semiring, &self.read_assumptions(condition), label)
+    }
+
+    /// Returns the probability of the conjunction of `query` given
+    /// `evidence` under the literal weights recorded in the graph, i.e., the
+    /// weighted count of `query` and `evidence` normalised by the weighted
+    /// count of `evidence`. Returns `None` if `evidence` has probability 0.
+    pub fn probability<S: ToString, T: ToString>(
+        &self,
+        query: impl Iterator<Item = S>,
+        evidence: impl Iterator<Item = T>,
+    ) -> Option<Rational> {
+        let mut assumptions = self.read_assumptions(evidence);
+        let weights = self.graph.weights();
+        let evidence_weight = self.graph.weighted_count(&assumptions, weights);
+        if evidence_weight == 0 {
+            return None;
+        }
+
+        assumptions.extend(self.read_assumptions(query));
+        Some(self.graph.weighted_count(&assumptions, weights) / evidence_weight)
+    }
+
+    /// Returns the marginal probability of every literal `a` and `~a` given
+    /// `evidence`, keyed by literal. Returns `None` if `evidence` has
+    /// probability 0.
+    pub fn marginal_probabilities<S: ToString>(
+        &self,
+        evidence: impl Iterator<Item = S>,
+    ) -> Option<HashMap<String, Rational>> {
+        let evidence = self.read_assumptions(evidence);
+        let (evidence_weight, marginals) = self
+            .graph
+            .weighted_marginals(&evidence, self.graph.weights());
+        if evidence_weight == 0 {
+            return None;
+        }
+
+        let mut probabilities = HashMap::with_capacity(2 * self.graph.mapping().len());
+        for (atom, var) in self.graph.mapping() {
+            let (pos, neg) = marginals
+                .get(var.unsigned_abs() as usize)
+                .cloned()
+                .unwrap_or_else(|| (evidence_weight.clone(), evidence_weight.clone()));
+            probabilities.insert(atom.clone(), pos / &evidence_weight);
+            probabilities.insert(format!("~{atom}"), neg / &evidence_weight);
+        }
+
+        Some(probabilities)
+    }
 
-        count
+    /// Iterates over the atoms of the program.
+    pub fn atoms(&self) -> impl Iterator<Item = &str> {
+        self.graph.mapping().keys().map(|a| a.as_str())
+    }
+
+    /// Returns the atoms of the program along with their cnf variables.
+    pub fn mapping(&self) -> &HashMap<String, i32> {
+        self.graph.mapping()
+    }
+
+    /// Returns the cnf literal of assumption `a`, `~a` or cnf literal, or
+    /// `None` if the atom is unknown.
+    pub fn resolve(&self, assumption: &str) -> Option<i32> {
+        self.read_assumption(assumption.to_owned())
+    }
+
+    /// Returns an evaluator that updates the count incrementally as
+    /// assumptions are pushed and popped.
+    pub fn incremental(&self) -> IncrementalCounter<'_> {
+        IncrementalCounter::new(self)
+    }
+
+    /// Returns the count under `condition` extended by `a` and by `~a` for
+    /// every atom `a`, keyed by literal, from a single marginal pass.
+    pub fn marginals<S: ToString>(
+        &self,
+        condition: impl Iterator<Item = S>,
+    ) -> HashMap<String, Integer> {
+        self.literal_counts(condition).1
     }
 
     /// For each literal `l` among `literals` prints answer set count under `l`.
     pub fn show_all(&self, literals: &[String], condition: &[String]) {
-        let mut counted = self.count(condition.iter());
+        let (counted, counts) = self.literal_counts(condition.iter());
         for lit in literals {
-            let count = self.count(condition.iter().chain([lit]));
+            let count = counts.get(lit).unwrap_or(&counted).clone();
             println!("{:.3} {lit}", log10_count(count));
         }
     }
@@ -165,16 +436,16 @@ impl Counter {
     ///
     /// NOTE: literals are assumed to be facets.
     pub fn find_max_among(&self, literals: &[String], condition: &[String]) -> Option<String> {
-        let mut counted = self.count(condition.iter());
+        let (counted, counts) = self.literal_counts(condition.iter());
         let (mut count, mut l, bound): (Integer, Option<String>, Integer) =
-            (Integer::ZERO, None, counted - 1);
+            (Integer::ZERO, None, Integer::from(&counted - 1));
         for lit in literals {
-            counted = self.count(condition.iter().chain([lit]));
-            if counted == bound {
+            let counted = counts.get(lit).unwrap_or(&counted);
+            if *counted == bound {
                 return Some(lit.to_string());
             }
-            if counted >= count {
-                count = counted;
+            if *counted >= count {
+                count = counted.clone();
                 l = Some(lit.to_string());
             }
         }
@@ -186,16 +457,16 @@ impl Counter {
     ///
     /// NOTE: literals are assumed to be facets.
     pub fn find_min_among(&self, literals: &[String], condition: &[String]) -> Option<String> {
-        let mut counted = self.count(condition.iter());
+        let (counted, counts) = self.literal_counts(condition.iter());
         let (mut count, mut l, bound): (Integer, Option<String>, Integer) =
-            (counted, None, Integer::ONE.clone());
+            (counted.clone(), None, Integer::ONE.clone());
         for lit in literals {
-            counted = self.count(condition.iter().chain([lit]));
-            if counted == bound {
+            let counted = counts.get(lit).unwrap_or(&counted);
+            if *counted == bound {
                 return Some(lit.to_string());
             }
-            if counted <= count {
-                count = counted;
+            if *counted <= count {
+                count = counted.clone();
                 l = Some(lit.to_string());
             }
         }
@@ -205,12 +476,17 @@ impl Counter {
 
     /// Returns overall count.
     pub fn overall_count(&self) -> f64 {
-        self.overall_count
+        self.graph.log10_count()
+    }
+
+    /// Returns node count.
+    pub fn node_count(&self) -> usize {
+        self.graph.node_count()
     }
 
     /// Returns facet count.
     pub fn facet_count<S: ToString>(&self, assume: impl Iterator<Item = S>) -> usize {
-        let mut curr = self.mapping.keys().collect::<HashSet<_>>();
+        let mut curr = self.graph.mapping().keys().collect::<HashSet<_>>();
 
         let mut base_assumptions = vec![];
         assume.for_each(|s| {
@@ -220,34 +496,54 @@ impl Counter {
             base_assumptions.push(str);
         });
 
+        let (_, counts) = self.literal_counts(base_assumptions.iter());
+
         curr.into_iter()
             .filter(|a| {
-                let str = a.to_string();
-                base_assumptions.push(format!("~{str}"));
-                let cond = if self.count(base_assumptions.iter()) > 0 {
-                    base_assumptions.pop();
-                    base_assumptions.push(str.clone());
-                    let p = self.count(base_assumptions.iter()) > 0;
-                    base_assumptions.pop();
-                    p
-                } else {
-                    base_assumptions.pop();
-                    false
-                };
-
-                cond
+                counts.get(&format!("~{a}")).is_some_and(|c| *c > 0)
+                    && counts.get(*a).is_some_and(|c| *c > 0)
             })
             .count()
     }
 
-    fn read_assumption(&self, assumption: String) -> Option<i32> {
-        match assumption.starts_with("~") {
-            true => self
-                .mapping
-                .get(&assumption[1..].to_owned())
-                .and_then(|i| Some(-i)),
-            _ => self.mapping.get(&assumption).copied(),
+    fn literal_counts<S: ToString>(
+        &self,
+        condition: impl Iterator<Item = S>,
+    ) -> (Integer, HashMap<String, Integer>) {
+        let (count, marginals) = self.graph.marginals(&self.read_assumptions(condition));
+
+        let mut counts = HashMap::with_capacity(2 * self.graph.mapping().len());
+        for (atom, var) in self.graph.mapping() {
+            let (pos, neg) = marginals
+                .get(*var as usize)
+                .cloned()
+                .unwrap_or_else(|| (count.clone(), count.clone()));
+            counts.insert(atom.clone(), pos);
+            counts.insert(format!("~{atom}"), neg);
         }
+
+        (count, counts)
+    }
+
+    fn read_assumptions<S: ToString>(&self, assume: impl Iterator<Item = S>) -> Vec<i32> {
+        assume
+            .filter_map(|s| self.read_assumption(s.to_string()))
+            .collect()
+    }
+
+    /// Reads assumption given as `a`, `~a` or cnf literal.
+    fn read_assumption(&self, assumption: String) -> Option<i32> {
+        read_literal(&assumption, self.graph.mapping())
+    }
+
+    /// Returns the atoms that are true in `model`, ordered by variable.
+    fn true_atoms(&self, model: &[i32]) -> Vec<String> {
+        let mut atoms = model
+            .iter()
+            .filter_map(|lit| self.symbols.get(lit).map(|atom| (lit, atom)))
+            .collect::<Vec<_>>();
+        atoms.sort();
+        atoms.into_iter().map(|(_, atom)| atom.clone()).collect()
     }
 }
 
@@ -270,10 +566,161 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn read_cnf_literals_and_names() -> Result<()> {
+        let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
+        assert_eq!(counter.resolve("-9"), Some(-9));
+        assert_eq!(counter.resolve("~b"), Some(-9));
+        assert_eq!(counter.resolve("~x"), None);
+        assert_eq!(counter.count(["-9", "a"].iter()), 2);
+        assert_eq!(
+            counter.count(["-9", "10"].iter()),
+            counter.count(["~b", "a"].iter())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn count_batch_in_order() -> Result<()> {
+        let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
+        let batch = [vec![], vec!["a"], vec!["~i", "b"], vec!["a", "b"]]
+            .map(|set| set.iter().map(|a| a.to_string()).collect::<Vec<_>>());
+        let counts = counter.count_batch(&batch);
+        assert_eq!(counts, vec![6, 2, 2, 0]);
+        for (set, count) in batch.iter().zip(&counts) {
+            assert_eq!(*count, counter.count(set.iter()));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn marginals_match_count() -> Result<()> {
+        let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
+        let marginals = counter.marginals(["~a"].iter());
+        for (lit, count) in &marginals {
+            assert_eq!(*count, counter.count(["~a", lit].iter()));
+        }
+        assert_eq!(counter.facet_count(["a"].iter()), 4);
+        assert_eq!(counter.facet_count(["~a"].iter()), 6);
+        Ok(())
+    }
+
+    #[test]
+    fn probability_of_facts() -> Result<()> {
+        let ccg = std::fs::read_to_string("examples/example.lp.as.cnf.nnf.ccg")
+            .map_err(|e| CounterError::ReadingError(e.to_string()))?;
+        let path = std::env::temp_dir().join("iascar_probability_of_facts.ccg");
+        std::fs::write(&path, ccg + "c p weight 10 3/10 0\nc p weight -10 0.7 0\n")
+            .map_err(|e| CounterError::ReadingError(e.to_string()))?;
+        let counter = Counter::new(&path)?;
+
+        let weights = counter.graph.weights();
+        let total = counter.graph.weighted_count(&[], weights);
+        let a = counter.graph.weighted_count(&[10], weights);
+        assert_eq!(
+            counter.probability(["a"].iter(), Vec::<&str>::new().iter()),
+            Some(a / &total)
+        );
+        assert_eq!(
+            counter.probability(["a"].iter(), ["a"].iter()),
+            Some(Rational::from(1))
+        );
+        assert_eq!(counter.probability(["a"].iter(), ["a", "~a"].iter()), None);
+
+        let marginals = counter
+            .marginal_probabilities(["~i"].iter())
+            .expect("~i is possible");
+        for (lit, p) in marginals {
+            assert_eq!(counter.probability([lit].iter(), ["~i"].iter()), Some(p));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn most_probable_answer_set() -> Result<()> {
+        let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
+        let weights = Weights::parse("a 1/3\n~a 2/3\n", counter.graph.mapping())
+            .map_err(|e| CounterError::ReadingError(e.to_string()))?;
+        let best = counter.most_probable(Vec::<String>::new().iter(), &weights, 2);
+        assert_eq!(best.len(), 2);
+        assert_eq!(best[0].0, Rational::from((2, 3)));
+        assert!(best
+            .iter()
+            .all(|(_, atoms)| !atoms.contains(&"a".to_owned())));
+
+        let cheapest = counter.min_cost(["a"].iter(), &weights, 1);
+        assert_eq!(cheapest[0].0, Rational::from((1, 3)));
+        assert!(cheapest[0].1.contains(&"a".to_owned()));
+        Ok(())
+    }
+
+    #[test]
+    fn sample_with_seed() -> Result<()> {
+        let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
+        let draw = |seed: u32| {
+            let mut rng = RandState::new();
+            rng.seed(&Integer::from(seed));
+            counter.sample(["~a"].iter(), 5, None, &mut rng)
+        };
+        let samples = draw(3);
+        assert_eq!(samples.len(), 5);
+        assert_eq!(samples, draw(3));
+        assert!(samples.iter().all(|atoms| !atoms.contains(&"a".to_owned())));
+        Ok(())
+    }
+
+    #[test]
+    fn models_by_name() -> Result<()> {
+        let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
+        let models = counter.models(["~b"].iter(), false).collect::<Vec<_>>();
+        assert_eq!(Integer::from(models.len()), counter.count(["~b"].iter()));
+        assert!(models.iter().all(|atoms| !atoms.contains(&"b".to_owned())));
+
+        let mut all = counter.models(Vec::<String>::new().iter(), true);
+        assert_eq!(all.next().map(|atoms| atoms.is_empty()), Some(false));
+        Ok(())
+    }
+
+    #[test]
+    fn count_with_denials() -> Result<()> {
+        let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
+        let constraints = ":- a, not e. :- b, not c.";
+        let expected = counter.count_query("~(a & ~e) & ~(b & ~c)", ["~h"].iter())?;
+        assert_eq!(
+            counter.count_denying(constraints, ["~h"].iter(), 0)?,
+            expected
+        );
+        assert_eq!(
+            counter.count_denying(constraints, ["~h"].iter(), 2)?,
+            expected
+        );
+        assert!(counter.count_denying(":- x.", ["~h"].iter(), 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rank_and_model_at() -> Result<()> {
+        let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
+        for (i, atoms) in counter.models(["~i"].iter(), false).enumerate() {
+            let index = Integer::from(i);
+            assert_eq!(
+                counter.rank(atoms.iter(), ["~i"].iter()),
+                Some(index.clone())
+            );
+            assert_eq!(counter.model_at(&index, ["~i"].iter()), Some(atoms));
+        }
+        assert_eq!(
+            counter.rank(["a", "b"].iter(), Vec::<String>::new().iter()),
+            None
+        );
+        Ok(())
+    }
+
     #[test]
     fn count_all() -> Result<()> {
         let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
-        counter.show_all(&[
+        counter.show_all(
+            &[
                 "a".to_owned(),
                 "~a".to_owned(),
                 "b".to_owned(),
@@ -290,7 +737,9 @@ mod tests {
                 "~h".to_owned(),
                 "i".to_owned(),
                 "~i".to_owned(),
-            ], &[]);
+            ],
+            &[],
+        );
         Ok(())
     }
 
@@ -299,85 +748,95 @@ mod tests {
         let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
         println!(
             "empty min {:?}",
-            counter.find_min_among(&[
-                "a".to_owned(),
-                "~a".to_owned(),
-                "b".to_owned(),
-                "~b".to_owned(),
-                "c".to_owned(),
-                "~c".to_owned(),
-                "d".to_owned(),
-                "~d".to_owned(),
-                "f".to_owned(),
-                "~f".to_owned(),
-                "g".to_owned(),
-                "~g".to_owned(),
-                "h".to_owned(),
-                "~h".to_owned(),
-                "i".to_owned(),
-                "~i".to_owned(),
-            ], &[])
+            counter.find_min_among(
+                &[
+                    "a".to_owned(),
+                    "~a".to_owned(),
+                    "b".to_owned(),
+                    "~b".to_owned(),
+                    "c".to_owned(),
+                    "~c".to_owned(),
+                    "d".to_owned(),
+                    "~d".to_owned(),
+                    "f".to_owned(),
+                    "~f".to_owned(),
+                    "g".to_owned(),
+                    "~g".to_owned(),
+                    "h".to_owned(),
+                    "~h".to_owned(),
+                    "i".to_owned(),
+                    "~i".to_owned(),
+                ],
+                &[]
+            )
         );
         println!(
             "empty max {:?}",
-            counter.find_max_among(&[
-                "a".to_owned(),
-                "~a".to_owned(),
-                "b".to_owned(),
-                "~b".to_owned(),
-                "c".to_owned(),
-                "~c".to_owned(),
-                "d".to_owned(),
-                "~d".to_owned(),
-                "f".to_owned(),
-                "~f".to_owned(),
-                "g".to_owned(),
-                "~g".to_owned(),
-                "h".to_owned(),
-                "~h".to_owned(),
-                "i".to_owned(),
-                "~i".to_owned(),
-            ], &[])
+            counter.find_max_among(
+                &[
+                    "a".to_owned(),
+                    "~a".to_owned(),
+                    "b".to_owned(),
+                    "~b".to_owned(),
+                    "c".to_owned(),
+                    "~c".to_owned(),
+                    "d".to_owned(),
+                    "~d".to_owned(),
+                    "f".to_owned(),
+                    "~f".to_owned(),
+                    "g".to_owned(),
+                    "~g".to_owned(),
+                    "h".to_owned(),
+                    "~h".to_owned(),
+                    "i".to_owned(),
+                    "~i".to_owned(),
+                ],
+                &[]
+            )
         );
         println!(
             "min ~a {:?}",
-            counter.find_min_among(&[
-                "b".to_owned(),
-                "~b".to_owned(),
-                "c".to_owned(),
-                "~c".to_owned(),
-                "d".to_owned(),
-                "~d".to_owned(),
-                "f".to_owned(),
-                "~f".to_owned(),
-                "g".to_owned(),
-                "~g".to_owned(),
-                "h".to_owned(),
-                "~h".to_owned(),
-                "i".to_owned(),
-                "~i".to_owned(),
-            ], &["~a".to_owned()])
+            counter.find_min_among(
+                &[
+                    "b".to_owned(),
+                    "~b".to_owned(),
+                    "c".to_owned(),
+                    "~c".to_owned(),
+                    "d".to_owned(),
+                    "~d".to_owned(),
+                    "f".to_owned(),
+                    "~f".to_owned(),
+                    "g".to_owned(),
+                    "~g".to_owned(),
+                    "h".to_owned(),
+                    "~h".to_owned(),
+                    "i".to_owned(),
+                    "~i".to_owned(),
+                ],
+                &["~a".to_owned()]
+            )
         );
         println!(
             "max b {:?}",
-            counter.find_max_among(&[
-                "a".to_owned(),
-                "~a".to_owned(),
-                "c".to_owned(),
-                "~c".to_owned(),
-                "d".to_owned(),
-                "~d".to_owned(),
-                "f".to_owned(),
-                "~f".to_owned(),
-                "g".to_owned(),
-                "~g".to_owned(),
-                "h".to_owned(),
-                "~h".to_owned(),
-                "i".to_owned(),
-                "~i".to_owned(),
-            ], &[
-                "b".to_owned(),
-            ])
+            counter.find_max_among(
+                &[
+                    "a".to_owned(),
+                    "~a".to_owned(),
+                    "c".to_owned(),
+                    "~c".to_owned(),
+                    "d".to_owned(),
+                    "~d".to_owned(),
+                    "f".to_owned(),
+                    "~f".to_owned(),
+                    "g".to_owned(),
+                    "~g".to_owned(),
+                    "h".to_owned(),
+                    "~h".to_owned(),
+                    "i".to_owned(),
+                    "~i".to_owned(),
+                ],
+                &["b".to_owned(),]
+            )
         );
         Ok(())
     }
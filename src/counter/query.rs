@@ -0,0 +1,277 @@
+use std::str::FromStr;
+
+use super::{CounterError, Result};
+
+/// Boolean formula over atom names.
+///
+/// Formulas are built from atoms, `~` (not), `&` (and), `|` (or), `->`
+/// (implication) and parentheses, binding in this order, where implication
+/// associates to the right. Atoms may contain parentheses directly after
+/// their name, e.g., `p(1,2) & ~q(a)`.
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Atom(String),
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Implies(Box<Query>, Box<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Atom(String),
+    Not,
+    And,
+    Or,
+    Implies,
+    Open,
+    Close,
+}
+
+#[allow(unused)]
+impl Query {
+    /// Returns the formula as disjunction of conjunctions of literals, where
+    /// `resolve` maps atoms to variables. Contradictory conjunctions are
+    /// omitted.
+    pub fn dnf(&self, resolve: &impl Fn(&str) -> Option<i32>) -> Result<Vec<Vec<i32>>> {
+        self.terms(false, resolve)
+    }
+
+    fn terms(
+        &self,
+        negated: bool,
+        resolve: &impl Fn(&str) -> Option<i32>,
+    ) -> Result<Vec<Vec<i32>>> {
+        Ok(match (self, negated) {
+            (Query::Atom(atom), _) => {
+                let var = resolve(atom).ok_or_else(|| {
+                    CounterError::ReadingError(format!("unknown atom {:?}", atom))
+                })?;
+                vec![vec![if negated { -var } else { var }]]
+            }
+            (Query::Not(q), _) => q.terms(!negated, resolve)?,
+            (Query::And(a, b), false) | (Query::Or(a, b), true) => {
+                conjoin(&a.terms(negated, resolve)?, &b.terms(negated, resolve)?)
+            }
+            (Query::Or(a, b), false) | (Query::And(a, b), true) => {
+                let mut terms = a.terms(negated, resolve)?;
+                terms.extend(b.terms(negated, resolve)?);
+                terms
+            }
+            (Query::Implies(a, b), false) => {
+                let mut terms = a.terms(true, resolve)?;
+                terms.extend(b.terms(false, resolve)?);
+                terms
+            }
+            (Query::Implies(a, b), true) => {
+                conjoin(&a.terms(false, resolve)?, &b.terms(true, resolve)?)
+            }
+        })
+    }
+
+    fn parse_implication(tokens: &[Token], pos: &mut usize) -> Result<Self> {
+        let lhs = Self::parse_disjunction(tokens, pos)?;
+        match tokens.get(*pos) {
+            Some(Token::Implies) => {
+                *pos += 1;
+                let rhs = Self::parse_implication(tokens, pos)?;
+                Ok(Query::Implies(Box::new(lhs), Box::new(rhs)))
+            }
+            _ => Ok(lhs),
+        }
+    }
+
+    fn parse_disjunction(tokens: &[Token], pos: &mut usize) -> Result<Self> {
+        let mut lhs = Self::parse_conjunction(tokens, pos)?;
+        while tokens.get(*pos) == Some(&Token::Or) {
+            *pos += 1;
+            let rhs = Self::parse_conjunction(tokens, pos)?;
+            lhs = Query::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_conjunction(tokens: &[Token], pos: &mut usize) -> Result<Self> {
+        let mut lhs = Self::parse_negation(tokens, pos)?;
+        while tokens.get(*pos) == Some(&Token::And) {
+            *pos += 1;
+            let rhs = Self::parse_negation(tokens, pos)?;
+            lhs = Query::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_negation(tokens: &[Token], pos: &mut usize) -> Result<Self> {
+        *pos += 1;
+        match tokens.get(*pos - 1) {
+            Some(Token::Not) => Ok(Query::Not(Box::new(Self::parse_negation(tokens, pos)?))),
+            Some(Token::Atom(atom)) => Ok(Query::Atom(atom.clone())),
+            Some(Token::Open) => {
+                let query = Self::parse_implication(tokens, pos)?;
+                match tokens.get(*pos) {
+                    Some(Token::Close) => {
+                        *pos += 1;
+                        Ok(query)
+                    }
+                    _ => Err(CounterError::ReadingError("missing ')'".to_owned())),
+                }
+            }
+            token => Err(CounterError::ReadingError(format!(
+                "unexpected {:?}",
+                token
+            ))),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = CounterError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let tokens = tokenize(s)?;
+        let mut pos = 0;
+        let query = Self::parse_implication(&tokens, &mut pos)?;
+        match tokens.get(pos) {
+            None => Ok(query),
+            token => Err(CounterError::ReadingError(format!(
+                "unexpected {:?}",
+                token
+            ))),
+        }
+    }
+}
+
+/// Returns the pairwise conjunctions of `lhs` and `rhs`.
+fn conjoin(lhs: &[Vec<i32>], rhs: &[Vec<i32>]) -> Vec<Vec<i32>> {
+    let mut terms = vec![];
+    for a in lhs {
+        for b in rhs {
+            let mut term = a.clone();
+            for lit in b {
+                if !term.contains(lit) {
+                    term.push(*lit);
+                }
+            }
+            if !term.iter().any(|l| term.contains(&-l)) {
+                terms.push(term);
+            }
+        }
+    }
+    terms
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let chars = s.chars().collect::<Vec<_>>();
+    let ends_atom = |i: usize| {
+        chars[i].is_whitespace()
+            || "~&|()".contains(chars[i])
+            || (chars[i] == '-' && chars.get(i + 1) == Some(&'>'))
+    };
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let token = match chars[i] {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '~' => Token::Not,
+            '&' => Token::And,
+            '|' => Token::Or,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '-' if chars.get(i + 1) == Some(&'>') => {
+                i += 1;
+                Token::Implies
+            }
+            _ => {
+                let start = i;
+                while i < chars.len() && !ends_atom(i) {
+                    i += 1;
+                }
+                // arguments of the atom
+                if chars.get(i) == Some(&'(') {
+                    let mut depth = 0;
+                    while i < chars.len() {
+                        match chars[i] {
+                            '(' => depth += 1,
+                            ')' => depth -= 1,
+                            _ => (),
+                        }
+                        i += 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    if depth != 0 {
+                        return Err(CounterError::ReadingError(format!(
+                            "unbalanced parentheses in {:?}",
+                            s
+                        )));
+                    }
+                }
+                tokens.push(Token::Atom(chars[start..i].iter().collect()));
+                continue;
+            }
+        };
+        tokens.push(token);
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Counter;
+
+    #[test]
+    fn parse_queries() -> Result<()> {
+        let atom = |s: &str| Box::new(Query::Atom(s.to_owned()));
+        assert_eq!(
+            "a & (c | ~d)".parse::<Query>()?,
+            Query::And(
+                atom("a"),
+                Box::new(Query::Or(atom("c"), Box::new(Query::Not(atom("d")))))
+            )
+        );
+        assert_eq!(
+            "p(1,f(2)) -> q | r->s".parse::<Query>()?,
+            Query::Implies(
+                atom("p(1,f(2))"),
+                Box::new(Query::Implies(
+                    Box::new(Query::Or(atom("q"), atom("r"))),
+                    atom("s")
+                ))
+            )
+        );
+        assert!("a & (b".parse::<Query>().is_err());
+        assert!("a b".parse::<Query>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn count_queries() -> Result<()> {
+        let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
+        let count = |a: &[&str]| counter.count(a.iter());
+        let none = Vec::<&str>::new();
+
+        assert_eq!(
+            counter.count_query("a & (c | ~d)", none.iter())?,
+            count(&["a"]) - count(&["a", "~c", "d"])
+        );
+        assert_eq!(
+            counter.count_query("a -> b", none.iter())?,
+            count(&[]) - count(&["a", "~b"])
+        );
+        assert_eq!(
+            counter.count_query("~(b | h)", ["~a"].iter())?,
+            count(&["~a", "~b", "~h"])
+        );
+        assert_eq!(counter.count_query("a & ~a", none.iter())?, 0);
+        assert!(counter.count_query("a & x", none.iter()).is_err());
+        Ok(())
+    }
+}
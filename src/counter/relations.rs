@@ -0,0 +1,146 @@
+use rug::Integer;
+
+use super::{signature, Counter};
+
+/// Relations between atoms that hold in every answer set under some
+/// assumptions.
+///
+/// Only facets are related: atoms true in every answer set would be implied
+/// by all others, and atoms true in none would imply all others.
+#[allow(unused)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Relations {
+    /// Classes of at least two atoms that are true in the same answer sets.
+    pub equivalent: Vec<Vec<String>>,
+    /// Pairs `(a, b)` where `a` implies `b` but not vice versa.
+    pub implications: Vec<(String, String)>,
+    /// Pairs of atoms that are never true together.
+    pub exclusive: Vec<(String, String)>,
+}
+
+#[allow(unused)]
+impl Counter {
+    /// Returns equivalent, implied and mutually exclusive atoms under
+    /// `condition`, or `None` if there is no answer set.
+    ///
+    /// The counts of all pairs involving a facet `a` are the marginals under
+    /// `condition` and `a`, so one marginal pass per class of equivalent
+    /// facets suffices. Pairs are only compared where their marginals admit
+    /// a relation: equivalent atoms have equal counts, `a` implies `b` only if
+    /// `b` is in at least as many answer sets, and exclusive atoms are in at
+    /// most as many answer sets combined as there are. Implications and
+    /// exclusions are reported between the first atoms of classes.
+    pub fn relations<S: ToString>(&self, condition: impl Iterator<Item = S>) -> Option<Relations> {
+        let assumptions = self.read_assumptions(condition);
+        let (count, marginals) = self.graph.marginals(&assumptions);
+        if count == 0 {
+            return None;
+        }
+
+        let mut facets = self
+            .graph
+            .mapping()
+            .iter()
+            .filter_map(|(atom, var)| {
+                let (pos, neg) = marginals.get(*var as usize)?;
+                (*pos > 0 && *neg > 0).then(|| (atom.clone(), *var as usize, pos.clone()))
+            })
+            .collect::<Vec<_>>();
+        facets.sort_by(|a, b| (signature(&a.0), &a.0).cmp(&(signature(&b.0), &b.0)));
+
+        // first facet of every class along with its class and joint counts
+        let mut classes: Vec<(usize, Vec<usize>, Vec<Integer>)> = vec![];
+        let mut placed = vec![false; facets.len()];
+        for (i, (_, var, marginal)) in facets.iter().enumerate() {
+            if placed[i] {
+                continue;
+            }
+            let mut under = assumptions.clone();
+            under.push(*var as i32);
+            let joint = self
+                .graph
+                .marginals(&under)
+                .1
+                .into_iter()
+                .map(|(pos, _)| pos)
+                .collect::<Vec<_>>();
+
+            let mut class = vec![i];
+            for (j, (_, other, m)) in facets.iter().enumerate().skip(i + 1) {
+                if !placed[j] && m == marginal && joint[*other] == *marginal {
+                    placed[j] = true;
+                    class.push(j);
+                }
+            }
+            classes.push((i, class, joint));
+        }
+
+        let mut relations = Relations::default();
+        for (k, (a, class, joint)) in classes.iter().enumerate() {
+            let (name_a, _, m_a) = &facets[*a];
+            if class.len() > 1 {
+                relations
+                    .equivalent
+                    .push(class.iter().map(|i| facets[*i].0.clone()).collect());
+            }
+            for (b, _, _) in &classes[k + 1..] {
+                let (name_b, var_b, m_b) = &facets[*b];
+                let both = &joint[*var_b];
+                if m_a <= m_b && both == m_a {
+                    relations
+                        .implications
+                        .push((name_a.clone(), name_b.clone()));
+                } else if m_b <= m_a && both == m_b {
+                    relations
+                        .implications
+                        .push((name_b.clone(), name_a.clone()));
+                } else if Integer::from(m_a + m_b) <= count && *both == 0 {
+                    relations.exclusive.push((name_a.clone(), name_b.clone()));
+                }
+            }
+        }
+
+        Some(relations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Result;
+
+    #[test]
+    fn relations_match_models() -> Result<()> {
+        let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
+        let relations = counter
+            .relations(Vec::<String>::new().iter())
+            .expect("satisfiable");
+        assert_eq!(relations.equivalent, vec![vec!["f", "g", "h"]]);
+
+        let models = counter
+            .models(Vec::<String>::new().iter(), false)
+            .collect::<Vec<_>>();
+        let holds = |f: &dyn Fn(bool, bool) -> bool, a: &str, b: &str| {
+            models.iter().all(|m| {
+                let has = |x: &str| m.iter().any(|y| y == x);
+                f(has(a), has(b))
+            })
+        };
+        for (a, b) in &relations.implications {
+            assert!(holds(&|x, y| !x || y, a, b));
+            assert!(!holds(&|x, y| !y || x, a, b));
+        }
+        for (a, b) in &relations.exclusive {
+            assert!(holds(&|x, y| !(x && y), a, b));
+        }
+        assert!(relations
+            .implications
+            .contains(&("c".to_owned(), "b".to_owned())));
+        assert!(relations
+            .exclusive
+            .contains(&("a".to_owned(), "b".to_owned())));
+
+        assert_eq!(counter.relations(["a", "~a"].iter()), None);
+        Ok(())
+    }
+}
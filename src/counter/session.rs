@@ -0,0 +1,303 @@
+use rug::Integer;
+use std::fs::{read_to_string, write};
+use std::path::{Path, PathBuf};
+
+use super::{signature, Counter, CounterError, IncrementalCounter, Result};
+
+/// Change of the active facets that can be undone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Activate(String),
+    Deactivate(String),
+}
+
+/// Faceted navigation through the answer sets of a [`Counter`].
+///
+/// Facets are activated as `a` (include) or `~a` (exclude), which restricts
+/// the answer sets to those containing respectively not containing `a`.
+/// Counts are maintained by an [`IncrementalCounter`], such that activating,
+/// deactivating and undoing only re-evaluate the affected nodes.
+/// Sessions are saved as text with one entry per line: the graph the counter
+/// was read from (`ccg <path>`), the active facets in activation order
+/// (`active <facet>`) and the steps that can be undone (`undo +|- <facet>`)
+/// and redone (`redo +|- <facet>`).
+#[allow(unused)]
+#[derive(Debug, Clone)]
+pub struct NavigationSession<'a> {
+    counter: &'a Counter,
+    evaluator: IncrementalCounter<'a>,
+    source: PathBuf,
+    active: Vec<String>,
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+}
+
+#[allow(unused)]
+impl<'a> NavigationSession<'a> {
+    /// Starts a session without active facets on `counter`, which was read
+    /// from graph `source`.
+    pub fn new(counter: &'a Counter, source: impl AsRef<Path>) -> Self {
+        Self {
+            counter,
+            evaluator: counter.incremental(),
+            source: source.as_ref().to_path_buf(),
+            active: vec![],
+            undo: vec![],
+            redo: vec![],
+        }
+    }
+
+    pub fn counter(&self) -> &'a Counter {
+        self.counter
+    }
+
+    /// Returns the active facets in activation order.
+    pub fn active(&self) -> &[String] {
+        &self.active
+    }
+
+    /// Returns the number of answer sets under the active facets.
+    pub fn count(&self) -> Integer {
+        self.evaluator.count()
+    }
+
+    /// Returns every facet `a` under the active facets along with the number
+    /// of answer sets when activating `a` and `~a`, sorted by predicate
+    /// signature and atom.
+    pub fn facets(&self) -> Vec<(String, Integer, Integer)> {
+        let (_, marginals) = self.counter.graph.marginals(self.evaluator.assumptions());
+
+        let mut facets = self
+            .counter
+            .graph
+            .mapping()
+            .iter()
+            .filter_map(|(atom, var)| {
+                let (pos, neg) = marginals.get(*var as usize)?;
+                (*pos > 0 && *neg > 0).then(|| (atom.clone(), pos.clone(), neg.clone()))
+            })
+            .collect::<Vec<_>>();
+        facets.sort_by(|a, b| (signature(&a.0), &a.0).cmp(&(signature(&b.0), &b.0)));
+
+        facets
+    }
+
+    /// Activates `facet` and returns the number of answer sets under the
+    /// active facets. Activating clears the steps that can be redone.
+    pub fn activate(&mut self, facet: &str) -> Result<Integer> {
+        if self.counter.resolve(facet).is_none() {
+            return Err(CounterError::ReadingError(format!(
+                "unknown facet {:?}",
+                facet
+            )));
+        }
+        if self.active.iter().any(|f| f == facet) {
+            return Err(CounterError::ReadingError(format!(
+                "facet {:?} is already active",
+                facet
+            )));
+        }
+        self.apply(&Step::Activate(facet.to_owned()));
+        self.undo.push(Step::Activate(facet.to_owned()));
+        self.redo.clear();
+
+        Ok(self.count())
+    }
+
+    /// Deactivates `facet` and returns the number of answer sets under the
+    /// remaining active facets.
+    pub fn deactivate(&mut self, facet: &str) -> Result<Integer> {
+        if !self.active.iter().any(|f| f == facet) {
+            return Err(CounterError::ReadingError(format!(
+                "facet {:?} is not active",
+                facet
+            )));
+        }
+        self.apply(&Step::Deactivate(facet.to_owned()));
+        self.undo.push(Step::Deactivate(facet.to_owned()));
+        self.redo.clear();
+
+        Ok(self.count())
+    }
+
+    /// Deactivates all facets; can be undone step by step.
+    pub fn clear(&mut self) {
+        while let Some(facet) = self.active.last().cloned() {
+            self.apply(&Step::Deactivate(facet.clone()));
+            self.undo.push(Step::Deactivate(facet));
+        }
+        self.redo.clear();
+    }
+
+    /// Reverts the last step, returning whether there was one.
+    pub fn undo(&mut self) -> bool {
+        match self.undo.pop() {
+            Some(step) => {
+                self.apply(&inverse(&step));
+                self.redo.push(step);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Repeats the last undone step, returning whether there was one.
+    pub fn redo(&mut self) -> bool {
+        match self.redo.pop() {
+            Some(step) => {
+                self.apply(&step);
+                self.undo.push(step);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Writes the session to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut session = format!("ccg {}\n", self.source.display());
+        for facet in &self.active {
+            session.push_str(&format!("active {facet}\n"));
+        }
+        for (kind, steps) in [("undo", &self.undo), ("redo", &self.redo)] {
+            for step in steps {
+                let (sign, facet) = match step {
+                    Step::Activate(facet) => ('+', facet),
+                    Step::Deactivate(facet) => ('-', facet),
+                };
+                session.push_str(&format!("{kind} {sign} {facet}\n"));
+            }
+        }
+
+        write(path, session).map_err(|err| CounterError::ReadingError(err.to_string()))
+    }
+
+    /// Returns the graph recorded in the session saved at `path`, to read
+    /// the counter to [`NavigationSession::restore`] it on.
+    pub fn source_of(path: impl AsRef<Path>) -> Result<PathBuf> {
+        let session =
+            read_to_string(path).map_err(|err| CounterError::ReadingError(err.to_string()))?;
+        session
+            .lines()
+            .next()
+            .and_then(|l| l.strip_prefix("ccg "))
+            .map(PathBuf::from)
+            .ok_or(CounterError::ReadingError(
+                "session does not start with graph".to_owned(),
+            ))
+    }
+
+    /// Restores a session written by [`NavigationSession::save`] on
+    /// `counter`, which is read from the graph returned by
+    /// [`NavigationSession::source_of`]. Fails on facets that `counter` does
+    /// not know.
+    pub fn restore(counter: &'a Counter, path: impl AsRef<Path>) -> Result<Self> {
+        let source = Self::source_of(&path)?;
+        let session =
+            read_to_string(path).map_err(|err| CounterError::ReadingError(err.to_string()))?;
+
+        let mut restored = Self::new(counter, source);
+        for line in session.lines().skip(1).filter(|l| !l.trim().is_empty()) {
+            let unreadable = || CounterError::ReadingError(format!("could not read {:?}", line));
+            let (kind, rest) = line.split_once(' ').ok_or_else(unreadable)?;
+            let step = match (kind, rest.split_once(' ')) {
+                ("active", _) => Step::Activate(rest.to_owned()),
+                ("undo" | "redo", Some(("+", facet))) => Step::Activate(facet.to_owned()),
+                ("undo" | "redo", Some(("-", facet))) => Step::Deactivate(facet.to_owned()),
+                _ => return Err(unreadable()),
+            };
+            let (Step::Activate(facet) | Step::Deactivate(facet)) = &step;
+            if counter.resolve(facet).is_none() {
+                return Err(CounterError::ReadingError(format!(
+                    "unknown facet {:?}",
+                    facet
+                )));
+            }
+            match kind {
+                "active" => restored.apply(&step),
+                "undo" => restored.undo.push(step),
+                _ => restored.redo.push(step),
+            }
+        }
+
+        Ok(restored)
+    }
+
+    fn apply(&mut self, step: &Step) {
+        match step {
+            Step::Activate(facet) => {
+                self.evaluator.push_assumption(facet);
+                self.active.push(facet.clone());
+            }
+            Step::Deactivate(facet) => {
+                if let Some(i) = self.active.iter().rposition(|f| f == facet) {
+                    self.evaluator.retract_assumption(facet);
+                    self.active.remove(i);
+                }
+            }
+        }
+    }
+}
+
+fn inverse(step: &Step) -> Step {
+    match step {
+        Step::Activate(facet) => Step::Deactivate(facet.clone()),
+        Step::Deactivate(facet) => Step::Activate(facet.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn navigate_undo_and_restore() -> Result<()> {
+        let example = "examples/example.lp.as.cnf.nnf.ccg";
+        let counter = Counter::new(example)?;
+        let mut session = NavigationSession::new(&counter, example);
+        assert_eq!(session.count(), 6);
+        assert_eq!(session.facets().len(), 8);
+
+        assert_eq!(session.activate("~i")?, 3);
+        assert_eq!(session.activate("b")?, 2);
+        assert!(session.activate("b").is_err());
+        assert!(session.activate("x").is_err());
+        let facets = session.facets();
+        assert_eq!(
+            facets
+                .iter()
+                .map(|(a, _, _)| a.as_str())
+                .collect::<Vec<_>>(),
+            vec!["c", "d"]
+        );
+        assert!(facets.iter().all(|(_, pos, neg)| *pos == 1 && *neg == 1));
+
+        assert_eq!(session.deactivate("~i")?, 4);
+        assert!(session.undo());
+        // reactivated facets count as activated last
+        assert_eq!(session.active(), ["b", "~i"]);
+        assert!(session.undo());
+        assert_eq!(session.count(), 3);
+        assert!(session.redo());
+        assert_eq!(session.count(), 2);
+
+        let path = std::env::temp_dir().join("iascar_navigate_undo_and_restore.session");
+        session.save(&path)?;
+        assert_eq!(NavigationSession::source_of(&path)?, Path::new(example));
+        let mut restored = NavigationSession::restore(&counter, &path)?;
+        assert_eq!(restored.active(), session.active());
+        assert!(restored.redo());
+        assert_eq!(restored.count(), 4);
+        assert!(!restored.redo());
+        restored.clear();
+        assert_eq!(restored.count(), 6);
+        while restored.undo() {}
+        assert_eq!(restored.count(), 6);
+        assert!(restored.active().is_empty());
+
+        write(&path, format!("ccg {example}\nactive b\nactive x\n")).unwrap();
+        assert!(NavigationSession::restore(&counter, &path).is_err());
+        std::fs::remove_file(path).unwrap();
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use rug::{Integer, Rational};
+
+use super::{signature, Counter};
+
+#[allow(unused)]
+impl Counter {
+    /// Returns the facet under `condition` whose activation splits the
+    /// answer sets closest to half, i.e., of maximal information gain, along
+    /// with the number of answer sets containing it and the gain in bits.
+    ///
+    /// Ties are broken by predicate signature and atom. Returns `None` if
+    /// there is no facet.
+    pub fn suggest<S: ToString>(
+        &self,
+        condition: impl Iterator<Item = S>,
+    ) -> Option<(String, Integer, f64)> {
+        let (count, marginals) = self.graph.marginals(&self.read_assumptions(condition));
+
+        self.graph
+            .mapping()
+            .iter()
+            .filter_map(|(atom, var)| {
+                let (pos, neg) = marginals.get(*var as usize)?;
+                (*pos > 0 && *neg > 0).then_some((atom, pos))
+            })
+            .min_by(|(a, x), (b, y)| {
+                let distance = |pos: &Integer| Integer::from(2 * pos - &count).abs();
+                (distance(x), signature(a), *a).cmp(&(distance(y), signature(b), *b))
+            })
+            .map(|(atom, pos)| {
+                let gain = entropy(&Rational::from((pos, &count)));
+                (atom.clone(), pos.clone(), gain)
+            })
+    }
+
+    /// Returns a sequence of facet activations, as `a` or `~a`, that narrows
+    /// the answer sets under `condition` down to a single one, along with the
+    /// number of answer sets after each step.
+    ///
+    /// Greedy: every step activates the suggested facet, taking the side
+    /// with more answer sets, such that the plan is as long as needed in the
+    /// worst case.
+    pub fn plan<S: ToString>(&self, condition: impl Iterator<Item = S>) -> Vec<(String, Integer)> {
+        let mut condition = condition.map(|s| s.to_string()).collect::<Vec<_>>();
+        let mut count = self.count(condition.iter());
+        let mut steps = vec![];
+
+        while let Some((atom, pos, _)) = self.suggest(condition.iter()) {
+            let neg = Integer::from(&count - &pos);
+            let (literal, rest) = match pos >= neg {
+                true => (atom, pos),
+                _ => (format!("~{atom}"), neg),
+            };
+            condition.push(literal.clone());
+            count = rest.clone();
+            steps.push((literal, rest));
+        }
+
+        steps
+    }
+}
+
+/// Returns the binary entropy of a split with probability `p` in bits.
+fn entropy(p: &Rational) -> f64 {
+    let p = p.to_f64();
+    [p, 1.0 - p]
+        .iter()
+        .filter(|x| **x > 0.0)
+        .map(|x| -x * x.log2())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Result;
+
+    #[test]
+    fn suggest_halving_facet() -> Result<()> {
+        let counter = Counter::new("examples/example.lp.as.cnf.nnf.ccg")?;
+        let none = Vec::<String>::new();
+        let (atom, count, gain) = counter.suggest(none.iter()).expect("facets");
+        assert_eq!(count, 3);
+        assert_eq!(count, counter.count([&atom].iter()));
+        assert_eq!(gain, 1.0);
+        assert_eq!(counter.suggest(["a", "h"].iter()), None);
+
+        let plan = counter.plan(none.iter());
+        let literals = plan.iter().map(|(l, _)| l.clone()).collect::<Vec<_>>();
+        assert_eq!(plan.last().map(|(_, c)| c.clone()), Some(Integer::from(1)));
+        assert_eq!(counter.count(literals.iter()), 1);
+        assert!(plan.windows(2).all(|w| w[0].1 > w[1].1));
+        Ok(())
+    }
+}
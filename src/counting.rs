@@ -1,381 +1,174 @@
-use crate::utils::ToHashSet;
+use crate::ccg::{
+    literal_names, parse_constraints, read_literal, Ccg, Counting, GapCounting, Weights,
+};
+use crate::compressor::read_cnf_mappings_;
 use itertools::Itertools;
 #[cfg(not(feature = "seq"))]
 use rayon::prelude::*;
-use rug::Integer;
+use rug::{Float, Integer, Rational};
 use savan::nav::Navigator;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs::read_to_string;
 use std::path::Path;
 use std::str::FromStr;
 
-pub fn count_on_sddnnf(filename: impl AsRef<Path>, assumptions: &[i32]) -> Integer {
+/// Resolves assumptions given as cnf literals, `a` or `~a` by `mapping`,
+/// exiting on unknown atoms.
+pub fn resolve_assumptions(assumptions: &[String], mapping: &HashMap<String, i32>) -> Vec<i32> {
+    assumptions
+        .iter()
+        .map(|a| {
+            read_literal(a, mapping).unwrap_or_else(|| {
+                println!("error: unknown atom {:?}.", a.trim_start_matches('~'));
+                std::process::exit(-1)
+            })
+        })
+        .collect()
+}
+
+/// Reads the atoms of nnf `filename` from the cnf it was compiled from, which
+/// is named like `filename` without extension `.nnf`, if some of
+/// `assumptions` is not a cnf literal. Exits if there is no such cnf.
+fn companion_mapping(filename: impl AsRef<Path>, assumptions: &[String]) -> HashMap<String, i32> {
+    if assumptions.iter().all(|a| i32::from_str(a).is_ok()) {
+        return HashMap::new();
+    }
+    let filename = filename.as_ref();
+    let cnf = match filename.extension().and_then(|e| e.to_str()) {
+        Some("nnf") => filename.with_extension(""),
+        _ => {
+            println!(
+                "error: atoms of {:?} are read from the cnf it was compiled from, expected nnf file {:?}.",
+                filename.display().to_string(),
+                "<cnf>.nnf"
+            );
+            std::process::exit(-1)
+        }
+    };
+    read_cnf_mappings_(&cnf).unwrap_or_else(|err| {
+        println!(
+            "error: could not read atoms from {:?}: {:?}.",
+            cnf.display().to_string(),
+            err
+        );
+        std::process::exit(-1)
+    })
+}
+
+/// Counts the models of the d-DNNF `filename`, which need not be smooth, over
+/// all variables declared in its header.
+pub fn count_on_sddnnf(filename: impl AsRef<Path>, assumptions: &[String]) -> Integer {
     let nnf = read_to_string(&filename).unwrap_or_else(|_| "".to_string());
+    let mapping = companion_mapping(&filename, assumptions);
+    let assumptions = &resolve_assumptions(assumptions, &mapping);
 
-    println!("c o a={:?}", assumptions);
+    println!("c o a={:?}", literal_names(assumptions, &mapping));
 
-    let mut lines = nnf.lines();
-    let mut stats = lines
-        .next()
-        .map(|line| line.split_whitespace())
-        .expect("reading nnf stats failed");
-    stats.next();
-    let node_count = stats
-        .next()
-        .and_then(|s| usize::from_str(s).ok())
-        .expect("reading node count failed.");
-    stats.next();
-    let var_count = stats
+    let var_count = nnf
+        .lines()
         .next()
+        .and_then(|line| line.split_whitespace().nth(3))
         .and_then(|s| usize::from_str(s).ok())
         .expect("reading var count failed.");
-
-    let (mut vals, mut vars) = (
-        Vec::with_capacity(node_count),
-        Vec::with_capacity(node_count),
-    );
-
-    let mut count = Integer::from(0);
-
-    lines.for_each(|line| {
-        let mut spec = line.split_whitespace();
-
-        match spec.next() {
-            Some("L") => {
-                let lit = spec
-                    .next()
-                    .and_then(|l| i32::from_str(l).ok())
-                    .expect("reading literal failed.");
-
-                match assumptions.contains(&-lit) {
-                    false => {
-                        count = Integer::from(1);
-                        vars.push(vec![Integer::from(lit).abs()].to_hashset());
-                    }
-                    _ => {
-                        count = Integer::from(0);
-                        vars.push(vec![].to_hashset());
-                    }
-                }
-
-                vals.push(count.clone());
-            }
-            Some("A") => {
-                let children_ids = spec
-                    .skip(1)
-                    .filter_map(|child| usize::from_str(child).ok())
-                    .collect::<Vec<_>>();
-
-                count = Integer::from(1);
-                let mut node_vars = HashSet::new();
-                children_ids.iter().for_each(|child_id| {
-                    node_vars = node_vars
-                        .union(unsafe { vars.get_unchecked(*child_id) })
-                        .cloned()
-                        .collect();
-                    count *= unsafe { vals.get_unchecked(*child_id) };
-                });
-
-                vars.push(node_vars);
-                vals.push(count.clone());
-            }
-            Some("O") => {
-                count = Integer::from(0);
-
-                let children_ids = spec
-                    .skip(2)
-                    .filter_map(|child| usize::from_str(child).ok())
-                    .collect::<Vec<_>>();
-
-                let node_vars = children_ids
-                    .iter()
-                    .map(|child_id| unsafe { vars.get_unchecked(*child_id) })
-                    .fold(HashSet::new(), |acc, set| {
-                        set.union(&acc).cloned().collect()
-                    });
-                let n_vars = node_vars.len();
-                children_ids
-                    .iter()
-                    .map(|child_id| (child_id, unsafe { vals.get_unchecked(*child_id) }))
-                    .for_each(|(child_id, val)| {
-                        let gap_size = n_vars - unsafe { vars.get_unchecked(*child_id) }.len();
-                        count += val.clone() << gap_size;
-                    });
-
-                vars.push(node_vars);
-                vals.push(count.clone());
-            }
-            _ => (),
-        }
+    let graph = Ccg::from_nnf(&nnf).expect("reading nnf failed.");
+    let (count, vars) = graph.evaluate(&GapCounting, assumptions, |lit, val| {
+        (Integer::from(val), HashSet::from([lit.unsigned_abs()]))
     });
 
-    let gap_size = var_count - unsafe { vars.get_unchecked(node_count - 1) }.len();
-    count << gap_size
+    count << (var_count - vars.len())
 }
 
-pub fn count_on_sddnnf_asp(filename: impl AsRef<Path>, assumptions: &[i32]) -> Integer {
+/// Counts the models of the smooth d-DNNF `filename`.
+pub fn count_on_sddnnf_asp(filename: impl AsRef<Path>, assumptions: &[String]) -> Integer {
     let nnf = read_to_string(&filename).unwrap_or_else(|_| "".to_string());
+    let mapping = companion_mapping(&filename, assumptions);
+    let assumptions = &resolve_assumptions(assumptions, &mapping);
 
-    println!("c o a={:?}", assumptions);
+    println!("c o a={:?}", literal_names(assumptions, &mapping));
 
-    let mut lines = nnf.lines();
-    let mut stats = lines
-        .next()
-        .map(|line| line.split_whitespace())
-        .expect("reading nnf stats failed");
-    stats.next();
-    let node_count = stats
-        .next()
-        .and_then(|s| usize::from_str(s).ok())
-        .expect("reading node count failed.");
-
-    let mut vals = Vec::with_capacity(node_count);
+    let graph = Ccg::from_nnf(&nnf).expect("reading nnf failed.");
+    graph.evaluate(&Counting, assumptions, |_, val| Integer::from(val))
+}
 
-    let mut count = Integer::from(0);
+pub fn count_on_ccg_io(ccg: impl AsRef<Path>, assumptions: &[String]) -> Integer {
+    let graph = Ccg::read(ccg).expect("reading ccg failed.");
+    let assumptions = &resolve_assumptions(assumptions, graph.mapping());
 
-    lines.for_each(|line| {
-        let mut spec = line.split_whitespace();
+    println!("c o a={:?}", literal_names(assumptions, graph.mapping()));
 
-        match spec.next() {
-            Some("L") => {
-                let lit = spec
-                    .next()
-                    .and_then(|l| i32::from_str(l).ok())
-                    .expect("reading literal failed.");
+    graph.count(assumptions)
+}
 
-                match assumptions.contains(&-lit) {
-                    false => {
-                        count = Integer::from(1);
-                    }
-                    _ => {
-                        count = Integer::from(0);
-                    }
-                }
+pub fn weighted_count_on_ccg_io(
+    ccg: impl AsRef<Path>,
+    weights: impl AsRef<Path>,
+    assumptions: &[String],
+) -> Rational {
+    let graph = Ccg::read(ccg).expect("reading ccg failed.");
+    let weights = Weights::read(weights, graph.mapping()).expect("reading weights failed.");
+    let assumptions = &resolve_assumptions(assumptions, graph.mapping());
 
-                vals.push(count.clone());
-            }
-            Some("A") => {
-                let children_ids = spec
-                    .skip(1)
-                    .filter_map(|child| usize::from_str(child).ok())
-                    .collect::<Vec<_>>();
-
-                count = Integer::from(1);
-                children_ids.iter().for_each(|child_id| {
-                    count *= unsafe { vals.get_unchecked(*child_id) };
-                });
-
-                vals.push(count.clone());
-            }
-            Some("O") => {
-                count = Integer::from(0);
-
-                let children_ids = spec
-                    .skip(2)
-                    .filter_map(|child| usize::from_str(child).ok())
-                    .collect::<Vec<_>>();
-
-                children_ids
-                    .iter()
-                    .map(|child_id| unsafe { vals.get_unchecked(*child_id) })
-                    .for_each(|val| {
-                        count += val.clone();
-                    });
-
-                vals.push(count.clone());
-            }
-            _ => (),
-        }
-    });
+    println!("c o a={:?}", literal_names(assumptions, graph.mapping()));
 
-    count
+    graph.weighted_count(assumptions, &weights)
 }
 
-pub fn count_on_ccg_io(ccg: impl AsRef<Path>, assumptions: &[i32]) -> Integer {
-    let ccg_nodes = read_to_string(ccg)
-        .unwrap()
-        .lines()
-        .into_iter()
-        .filter(|l| !l.starts_with('c'))
-        .map(|s| s.to_string())
-        .collect::<Vec<_>>();
-    let node_count = ccg_nodes.len();
-
-    println!("c o a={:?}", assumptions);
-
-    let mut nodes = Vec::with_capacity(node_count);
-
-    let mut count = Integer::from(0);
-
-    for node in ccg_nodes {
-        let mut spec = node.split_whitespace();
-        match spec.next() {
-            Some("*") => {
-                let n_children = spec
-                    .next()
-                    .and_then(|child| usize::from_str(child).ok())
-                    .unwrap();
-
-                let children_ = &spec
-                    .flat_map(|child| usize::from_str(child).ok())
-                    .collect::<Vec<_>>()[..n_children];
-                let children = children_
-                    .iter()
-                    .map(|idx| unsafe { nodes.get_unchecked(*idx) })
-                    .collect::<Vec<_>>();
-
-                count = children
-                    .iter()
-                    .fold(Integer::from(1), |acc, child_val: &&Integer| {
-                        acc * &(*child_val).clone()
-                    });
-
-                nodes.push(count.clone());
-            }
-            Some("+") => {
-                count = Integer::from(0);
-                let n_children = spec
-                    .next()
-                    .and_then(|child| usize::from_str(child).ok())
-                    .unwrap();
-
-                let children_ = &spec
-                    .flat_map(|child| usize::from_str(child).ok())
-                    .collect::<Vec<_>>()[..n_children];
-                let children = children_
-                    .iter()
-                    .map(|idx| unsafe { nodes.get_unchecked(*idx) })
-                    .collect::<Vec<_>>();
-
-                children.iter().for_each(|child_val| {
-                    count += &(*child_val).clone();
-                });
-
-                nodes.push(count.clone());
-            }
-            o => {
-                let lit = o
-                    .and_then(|l| i32::from_str(l).ok())
-                    .expect("reading literal failed.");
-
-                count = spec
-                    .next()
-                    .and_then(|l| i32::from_str(l).map(Integer::from).ok())
-                    .expect("reading val failed.");
-
-                match assumptions.contains(&-lit) {
-                    false => {
-                        nodes.push(count.clone());
-                    }
-                    _ => {
-                        count = Integer::from(0);
-                        nodes.push(count.clone());
-                    }
-                }
-            }
-        }
-    }
+pub fn weighted_count_float_on_ccg_io(
+    ccg: impl AsRef<Path>,
+    weights: impl AsRef<Path>,
+    assumptions: &[String],
+    prec: u32,
+) -> Float {
+    let graph = Ccg::read(ccg).expect("reading ccg failed.");
+    let weights = Weights::read(weights, graph.mapping()).expect("reading weights failed.");
+    let assumptions = &resolve_assumptions(assumptions, graph.mapping());
 
-    count
+    println!(
+        "c o p={:?} a={:?}",
+        prec,
+        literal_names(assumptions, graph.mapping())
+    );
+
+    graph.weighted_count_float(assumptions, &weights, prec)
 }
 
-fn count_on_ccg(ccg: &[String], assumptions: &[i32]) -> Integer {
-    let ccg_nodes = ccg.iter().collect::<Vec<_>>();
-    let node_count = ccg_nodes.len();
-
-    let mut nodes = Vec::with_capacity(node_count);
-
-    let mut count = Integer::from(0);
-
-    for node in ccg_nodes {
-        let mut spec = node.split_whitespace();
-        match spec.next() {
-            Some("*") => {
-                let n_children = spec
-                    .next()
-                    .and_then(|child| usize::from_str(child).ok())
-                    .unwrap();
-
-                let children_ = &spec
-                    .flat_map(|child| usize::from_str(child).ok())
-                    .collect::<Vec<_>>()[..n_children];
-                let children = children_
-                    .iter()
-                    .map(|idx| unsafe { nodes.get_unchecked(*idx) })
-                    .collect::<Vec<_>>();
-
-                count = children
-                    .iter()
-                    .fold(Integer::from(1), |acc, child_val: &&Integer| {
-                        acc * &(*child_val).clone()
-                    });
-
-                nodes.push(count.clone());
-            }
-            Some("+") => {
-                count = Integer::from(0);
-                let n_children = spec
-                    .next()
-                    .and_then(|child| usize::from_str(child).ok())
-                    .unwrap();
-
-                let children_ = &spec
-                    .flat_map(|child| usize::from_str(child).ok())
-                    .collect::<Vec<_>>()[..n_children];
-                let children = children_
-                    .iter()
-                    .map(|idx| unsafe { nodes.get_unchecked(*idx) })
-                    .collect::<Vec<_>>();
-
-                children.iter().for_each(|child_val| {
-                    count += &(*child_val).clone();
-                });
-
-                nodes.push(count.clone());
-            }
-            o => {
-                let lit = o
-                    .and_then(|l| i32::from_str(l).ok())
-                    .expect("reading literal failed.");
-
-                count = spec
-                    .next()
-                    .and_then(|l| i32::from_str(l).map(Integer::from).ok())
-                    .expect("reading val failed.");
-
-                match assumptions.contains(&-lit) {
-                    false => {
-                        nodes.push(count.clone());
-                    }
-                    _ => {
-                        count = Integer::from(0);
-                        nodes.push(count.clone());
-                    }
-                }
-            }
-        }
-    }
+pub fn count_denying_on_ccg_io(
+    ccg: impl AsRef<Path>,
+    constraints: &str,
+    assumptions: &[String],
+    depth: usize,
+) -> Integer {
+    let graph = Ccg::read(ccg).expect("reading ccg failed.");
+    let denied =
+        parse_constraints(constraints, graph.mapping()).expect("reading constraints failed.");
+    let assumptions = &resolve_assumptions(assumptions, graph.mapping());
 
-    count
+    println!(
+        "c o d={:?} n={:?} a={:?}",
+        depth,
+        denied.len(),
+        literal_names(assumptions, graph.mapping())
+    );
+
+    match depth {
+        0 => graph.count_denying(&denied, assumptions),
+        _ => graph.count_denying_to_depth(&denied, assumptions, depth),
+    }
 }
 
 pub fn anytime_cg_count(
     ccg: impl AsRef<Path>,
     cycles: std::str::Lines,
-    assumptions: &[i32],
+    assumptions: &[String],
     depth: usize,
 ) -> Integer {
     let cycles_file = cycles.collect::<Vec<_>>();
 
-    let ccg_nodes = read_to_string(ccg)
-        .unwrap()
-        .lines()
-        .into_iter()
-        .filter(|l| !l.starts_with('c'))
-        .map(|s| s.to_string())
-        .collect::<Vec<_>>();
+    let graph = Ccg::read(ccg).expect("reading ccg failed.");
+    let assumptions = &resolve_assumptions(assumptions, graph.mapping());
+    let mut vals = vec![];
 
-    let mut count = count_on_ccg(&ccg_nodes, assumptions);
+    let mut count = graph.count_with(assumptions, &mut vals);
 
     /*
     let ucs = cycles_file
@@ -421,7 +214,12 @@ pub fn anytime_cg_count(
     #[cfg(not(feature = "prefilter"))]
     let d = if depth == 0 { n_cycles + 1 } else { depth + 1 };
     #[cfg(not(feature = "prefilter"))]
-    println!("c o d={:?} n={:?} a={:?}", d - 1, n_cycles, assumptions);
+    println!(
+        "c o d={:?} n={:?} a={:?}",
+        d - 1,
+        n_cycles,
+        literal_names(assumptions, graph.mapping())
+    );
     #[cfg(feature = "seq")]
     print!("c o +seq");
     #[cfg(not(feature = "seq"))]
@@ -444,7 +242,7 @@ pub fn anytime_cg_count(
         d - 1,
         n_unfiltered,
         n_cycles,
-        assumptions
+        literal_names(assumptions, graph.mapping())
     );
 
     if count == 0 {
@@ -465,7 +263,7 @@ pub fn anytime_cg_count(
             let mut u_ = u.clone();
             u_.extend(assumptions);
 
-            let c = count_on_ccg(&ccg_nodes, &u);
+            let c = graph.count_with(&u, &mut vals);
 
             #[cfg(feature = "dbg")]
             println!(":: {:?} {:?}", j, c);
@@ -506,14 +304,14 @@ pub fn anytime_cg_count(
                             a
                         });
                     assumptions_.extend(assumptions);
-                    count -= count_on_ccg(&ccg_nodes, &assumptions_);
+                    count -= graph.count_with(&assumptions_, &mut vals);
                 }
 
                 #[cfg(not(feature = "seq"))]
                 {
                     let c = lambda_i
                         .par_iter()
-                        .map(|gamma| {
+                        .map_init(Vec::new, |vals, gamma| {
                             let mut assumptions_: Vec<i32> = gamma
                                 .iter()
                                 .map(|idx| unsafe { ucs.get_unchecked(*idx) })
@@ -522,7 +320,7 @@ pub fn anytime_cg_count(
                                     a
                                 });
                             assumptions_.extend(assumptions);
-                            count_on_ccg(&ccg_nodes, &assumptions_)
+                            graph.count_with(&assumptions_, vals)
                         })
                         .sum::<Integer>();
                     count -= c;
@@ -541,14 +339,14 @@ pub fn anytime_cg_count(
                             a
                         });
                     assumptions_.extend(assumptions);
-                    count += count_on_ccg(&ccg_nodes, &assumptions_);
+                    count += graph.count_with(&assumptions_, &mut vals);
                 }
 
                 #[cfg(not(feature = "seq"))]
                 {
                     let c = lambda_i
                         .par_iter()
-                        .map(|gamma| {
+                        .map_init(Vec::new, |vals, gamma| {
                             let mut assumptions_: Vec<i32> = gamma
                                 .iter()
                                 .map(|idx| unsafe { ucs.get_unchecked(*idx) })
@@ -557,7 +355,7 @@ pub fn anytime_cg_count(
                                     a
                                 });
                             assumptions_.extend(assumptions);
-                            count_on_ccg(&ccg_nodes, &assumptions_)
+                            graph.count_with(&assumptions_, vals)
                         })
                         .sum::<Integer>();
                     count += c;
@@ -604,15 +402,10 @@ pub fn anytime_cg_count_with_filtering(
 ) -> Integer {
     let cycles_file = cycles.collect::<Vec<_>>();
 
-    let ccg_nodes = read_to_string(ccg)
-        .unwrap()
-        .lines()
-        .into_iter()
-        .filter(|l| !l.starts_with('c'))
-        .map(|s| s.to_string())
-        .collect::<Vec<_>>();
+    let graph = Ccg::read(ccg).expect("reading ccg failed.");
+    let mut vals = vec![];
 
-    let mut count = count_on_ccg(&ccg_nodes, assumptions);
+    let mut count = graph.count_with(assumptions, &mut vals);
 
     let mut n_unfiltered = 0;
     let mut ucs = cycles_file
@@ -682,7 +475,7 @@ pub fn anytime_cg_count_with_filtering(
                             a.extend(v);
                             a
                         });
-                    let effect = count_on_ccg(&ccg_nodes, &assumptions_);
+                    let effect = graph.count_with(&assumptions_, &mut vals);
                     if effect != 0 {
                         count -= effect;
                         effective_ucs.push(assumptions_);
@@ -702,7 +495,7 @@ pub fn anytime_cg_count_with_filtering(
                             a.extend(v);
                             a
                         });
-                    let effect = count_on_ccg(&ccg_nodes, &assumptions_);
+                    let effect = graph.count_with(&assumptions_, &mut vals);
                     if effect != 0 {
                         count += effect;
                         effective_ucs.push(assumptions_);
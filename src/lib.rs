@@ -1,3 +1,4 @@
+pub mod ccg;
 pub mod compressor;
 pub mod counter;
 mod utils;
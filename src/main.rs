@@ -1,13 +1,14 @@
 #![deny(clippy::all)]
 
+mod ccg;
 mod compressor;
 mod counter;
 mod counting;
+mod repl;
+mod server;
 mod utils;
 
-use std::env::Args;
 use std::fs::read_to_string;
-use std::iter::Skip;
 use std::str::FromStr;
 
 #[allow(unused)]
@@ -19,19 +20,23 @@ pub(crate) const SAND: &'static str = "*";
 #[allow(unused)]
 pub(crate) const SOR: &'static str = "+";
 
-fn read_assumptions(mut args: Skip<Args>) -> Vec<i32> {
+/// Reads assumptions given as cnf literals, `a` or `~a` with `-a` or from a
+/// file with `-fa`.
+fn read_assumptions(mut args: impl Iterator<Item = String>) -> Vec<String> {
     match args.next().as_deref() {
         Some("-a") => args
-            .map(|l| i32::from_str(l.trim()).ok())
-            .flatten()
+            .flat_map(|l| {
+                l.split_whitespace()
+                    .map(|a| a.to_owned())
+                    .collect::<Vec<_>>()
+            })
             .collect::<Vec<_>>(),
         Some("-fa") => args
             .next()
             .and_then(|f| read_to_string(f).ok())
             .map(|s| {
                 s.split_whitespace()
-                    .map(|l| i32::from_str(l.trim()).ok())
-                    .flatten()
+                    .map(|a| a.to_owned())
                     .collect::<Vec<_>>()
             })
             .unwrap_or(vec![]),
@@ -53,7 +58,46 @@ fn main() {
                     std::process::exit(-1)
                 },
                 |f| {
-                    let count = counting::count_on_ccg_io(f, &read_assumptions(args));
+                    let mut args = args.peekable();
+                    let constraints = match args.peek().map(|s| s.as_str()) {
+                        Some("-deny") => {
+                            args.next();
+                            args.next()
+                        }
+                        Some("-fdeny") => {
+                            args.next();
+                            args.next().map(|path| {
+                                read_to_string(&path).unwrap_or_else(|err| {
+                                    println!("error: {:?}.", err.to_string());
+                                    std::process::exit(-1)
+                                })
+                            })
+                        }
+                        _ => None,
+                    };
+                    let depth = match args.peek().map(|s| s.as_str()) {
+                        Some("-dep") => {
+                            args.next();
+                            match args.next().as_deref().map(usize::from_str) {
+                                Some(Ok(u)) => u,
+                                _ => {
+                                    println!("error: provide depth with {:?}.", "-dep int");
+                                    std::process::exit(-1)
+                                }
+                            }
+                        }
+                        _ => 0,
+                    };
+
+                    let count = match constraints {
+                        Some(constraints) => counting::count_denying_on_ccg_io(
+                            f,
+                            &constraints,
+                            &read_assumptions(args),
+                            depth,
+                        ),
+                        _ => counting::count_on_ccg_io(f, &read_assumptions(args)),
+                    };
                     if count > rug::Integer::from(0) {
                         println!("s SATISFIABLE");
                         println!("c s log10-estimate {:?}", count.to_f64().log10());
@@ -80,11 +124,14 @@ fn main() {
                     None
                 }
             })
-            .map(|((lp, cnf), nnf)| compressor::compress_(nnf, lp, cnf))
+            .map(|((lp, cnf), nnf)| match args.next().as_deref() {
+                Some("-min") => compressor::compress_minimized_(nnf, lp, cnf),
+                _ => compressor::compress_(nnf, lp, cnf),
+            })
             .unwrap_or_else(|| {
                 println!(
                     "error: please provide input in the following order {:?}.",
-                    "-lp logic_program_path -cnf cnf_path -nnf nnf_path"
+                    "-lp logic_program_path -cnf cnf_path -nnf nnf_path [-min]"
                 );
                 std::process::exit(-1)
             })
@@ -94,6 +141,51 @@ fn main() {
                 std::process::exit(-1)
             })
             .unwrap_or(()),
+        Some("-strip") => args
+            .next()
+            .and_then(|s| if s == "-lp" { args.next() } else { None })
+            .map_or_else(
+                || {
+                    println!("error: provide logic program path with {:?}.", "-lp path");
+                    std::process::exit(-1)
+                },
+                |lp| match read_to_string(lp)
+                    .map_err(compressor::CompressorError::from)
+                    .and_then(|lp| compressor::strip_probabilistic_facts(&lp))
+                {
+                    Ok((lp, _)) => print!("{}", lp),
+                    Err(err) => {
+                        println!("error: {:?}.", err);
+                        std::process::exit(-1)
+                    }
+                },
+            ),
+        Some("-convert") => args
+            .next()
+            .and_then(|s| if s.trim() == "-in" { args.next() } else { None })
+            .map_or_else(
+                || {
+                    println!("error: provide ccg file path with {:?}.", "-in path");
+                    std::process::exit(-1)
+                },
+                |f| {
+                    let stdout = std::io::stdout();
+                    let mut handle = stdout.lock();
+                    ccg::Ccg::is_binary(&f)
+                        .and_then(|binary| {
+                            ccg::Ccg::verify(&f)?;
+                            let graph = ccg::Ccg::read(&f)?;
+                            match binary {
+                                true => graph.write_text(&mut handle),
+                                _ => graph.write_binary(&mut handle),
+                            }
+                        })
+                        .unwrap_or_else(|err| {
+                            println!("error: {:?}.", err.to_string());
+                            std::process::exit(-1)
+                        })
+                },
+            ),
         Some("-car") => args
             .next()
             .and_then(|s| if s == "-ccg" { args.next() } else { None })
@@ -141,6 +233,844 @@ fn main() {
                     }
                 },
             ),
+        Some("-wmc") => {
+            let mut args = args.peekable();
+            let ccg = args
+                .next()
+                .and_then(|s| if s == "-ccg" { args.next() } else { None });
+            let weights = if args.next().as_deref() == Some("-w") {
+                args.next()
+            } else {
+                None
+            };
+            let prec = match args.peek().map(|s| s.as_str()) {
+                Some("-prec") => {
+                    args.next();
+                    match args.next().as_deref().map(u32::from_str) {
+                        Some(Ok(p))
+                            if (rug::float::prec_min()..=rug::float::prec_max()).contains(&p) =>
+                        {
+                            Some(p)
+                        }
+                        Some(Ok(_)) => {
+                            println!(
+                                "error: provide precision between {:?} and {:?} bits.",
+                                rug::float::prec_min(),
+                                rug::float::prec_max()
+                            );
+                            std::process::exit(-1)
+                        }
+                        Some(Err(e)) => {
+                            println!("error: {:?}.", e.to_string());
+                            std::process::exit(-1)
+                        }
+                        _ => {
+                            println!("error: provide precision with {:?}.", "-prec int");
+                            std::process::exit(-1)
+                        }
+                    }
+                }
+                _ => None,
+            };
+            match ccg.zip(weights) {
+                None => {
+                    println!(
+                        "error: please provide input in the following order {:?}.",
+                        "-ccg counting_graph -w weights [-prec bits]"
+                    );
+                    std::process::exit(-1)
+                }
+                Some((ccg, weights)) => match prec {
+                    Some(prec) => {
+                        let wmc = counting::weighted_count_float_on_ccg_io(
+                            ccg,
+                            weights,
+                            &read_assumptions(args),
+                            prec,
+                        );
+                        if wmc > 0 {
+                            println!("s SATISFIABLE");
+                            println!("c s log10-estimate {:?}", wmc.to_f64().log10());
+                            println!("c s float {}", wmc);
+                        } else {
+                            println!("s UNSATISFIABLE")
+                        }
+                    }
+                    _ => {
+                        let wmc = counting::weighted_count_on_ccg_io(
+                            ccg,
+                            weights,
+                            &read_assumptions(args),
+                        );
+                        if wmc > 0 {
+                            println!("s SATISFIABLE");
+                            println!("c s log10-estimate {:?}", wmc.to_f64().log10());
+                            println!("c s exact rational {}", wmc);
+                        } else {
+                            println!("s UNSATISFIABLE")
+                        }
+                    }
+                },
+            }
+        }
+        Some("-prob") => {
+            let ccg = args
+                .next()
+                .and_then(|s| if s == "-ccg" { args.next() } else { None });
+            let (mut query, mut evidence) = (vec![], vec![]);
+            let mut target = &mut query;
+            for arg in args {
+                match arg.as_str() {
+                    "-q" => target = &mut query,
+                    "-e" => target = &mut evidence,
+                    _ => target.push(arg),
+                }
+            }
+            ccg.map_or_else(
+                || {
+                    println!(
+                        "error: please provide input in the following order {:?}.",
+                        "-ccg counting_graph [-q atoms] [-e evidence]"
+                    );
+                    std::process::exit(-1)
+                },
+                |ccg| {
+                    let counter = counter::Counter::new(ccg).unwrap_or_else(|err| {
+                        println!("error: {:?}.", err);
+                        std::process::exit(-1)
+                    });
+                    let evidence = counting::resolve_assumptions(&evidence, counter.mapping());
+                    let mut query = ccg::literal_names(
+                        &counting::resolve_assumptions(&query, counter.mapping()),
+                        counter.mapping(),
+                    );
+                    if let Some(q) = query
+                        .iter()
+                        .find(|q| !counter.mapping().contains_key(q.trim_start_matches('~')))
+                    {
+                        println!("error: unknown atom {:?}.", q.trim_start_matches('~'));
+                        std::process::exit(-1)
+                    }
+                    println!(
+                        "c o q={:?} e={:?}",
+                        query,
+                        ccg::literal_names(&evidence, counter.mapping())
+                    );
+                    match counter.marginal_probabilities(evidence.iter()) {
+                        Some(marginals) => {
+                            println!("s SATISFIABLE");
+                            if query.is_empty() {
+                                let mut atoms = counter.atoms().collect::<Vec<_>>();
+                                atoms.sort();
+                                query = atoms.into_iter().map(|a| a.to_owned()).collect();
+                            }
+                            for q in &query {
+                                let p = &marginals[q];
+                                println!("c p {} {:?} {}", q, p.to_f64(), p);
+                            }
+                        }
+                        _ => println!("s UNSATISFIABLE"),
+                    }
+                },
+            )
+        }
+        Some(mode @ ("-mpe" | "-mincost")) => {
+            let mode = mode.to_owned();
+            let mut args = args.peekable();
+            let ccg = args
+                .next()
+                .and_then(|s| if s == "-ccg" { args.next() } else { None });
+            let weights = match args.peek().map(|s| s.as_str()) {
+                Some("-w") => {
+                    args.next();
+                    args.next()
+                }
+                _ => None,
+            };
+            let k = match args.peek().map(|s| s.as_str()) {
+                Some("-k") => {
+                    args.next();
+                    match args.next().as_deref().map(usize::from_str) {
+                        Some(Ok(k)) => k,
+                        _ => {
+                            println!("error: provide number of answer sets with {:?}.", "-k int");
+                            std::process::exit(-1)
+                        }
+                    }
+                }
+                _ => 1,
+            };
+            let ccg = ccg.unwrap_or_else(|| {
+                println!(
+                    "error: please provide input in the following order {:?}.",
+                    "-ccg counting_graph [-w weights] [-k int]"
+                );
+                std::process::exit(-1)
+            });
+            let counter = counter::Counter::new(ccg).unwrap_or_else(|err| {
+                println!("error: {:?}.", err);
+                std::process::exit(-1)
+            });
+            let weights = match weights {
+                Some(path) => counter.read_weights(path).unwrap_or_else(|err| {
+                    println!("error: {:?}.", err);
+                    std::process::exit(-1)
+                }),
+                _ => counter.weights().clone(),
+            };
+            let assumptions =
+                counting::resolve_assumptions(&read_assumptions(args), counter.mapping());
+            println!(
+                "c o k={:?} a={:?}",
+                k,
+                ccg::literal_names(&assumptions, counter.mapping())
+            );
+
+            let (answer_sets, objective) = match mode.as_str() {
+                "-mpe" => (
+                    counter.most_probable(assumptions.iter(), &weights, k),
+                    "weight",
+                ),
+                _ => (counter.min_cost(assumptions.iter(), &weights, k), "cost"),
+            };
+            match answer_sets.is_empty() {
+                true => println!("s UNSATISFIABLE"),
+                _ => {
+                    println!("s SATISFIABLE");
+                    for (i, (value, atoms)) in answer_sets.iter().enumerate() {
+                        println!("c s {} {} {} {:?}", i + 1, objective, value, value.to_f64());
+                        println!("v {}", atoms.join(" "));
+                    }
+                }
+            }
+        }
+        Some("-sample") => {
+            let n = args.next().as_deref().map(usize::from_str);
+            let mut args = args.peekable();
+            let ccg = args
+                .next()
+                .and_then(|s| if s == "-ccg" { args.next() } else { None });
+            let (n, ccg) = match (n, ccg) {
+                (Some(Ok(n)), Some(ccg)) => (n, ccg),
+                _ => {
+                    println!(
+                        "error: please provide input in the following order {:?}.",
+                        "N -ccg counting_graph [-w weights] [-seed int]"
+                    );
+                    std::process::exit(-1)
+                }
+            };
+            let weights = match args.peek().map(|s| s.as_str()) {
+                Some("-w") => {
+                    args.next();
+                    args.next()
+                }
+                _ => None,
+            };
+            let seed = match args.peek().map(|s| s.as_str()) {
+                Some("-seed") => {
+                    args.next();
+                    match args.next().as_deref().map(u64::from_str) {
+                        Some(Ok(seed)) => seed,
+                        _ => {
+                            println!("error: provide seed with {:?}.", "-seed int");
+                            std::process::exit(-1)
+                        }
+                    }
+                }
+                _ => std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or(0),
+            };
+
+            let counter = counter::Counter::new(ccg).unwrap_or_else(|err| {
+                println!("error: {:?}.", err);
+                std::process::exit(-1)
+            });
+            let weights = weights.map(|path| {
+                counter.read_weights(path).unwrap_or_else(|err| {
+                    println!("error: {:?}.", err);
+                    std::process::exit(-1)
+                })
+            });
+            let assumptions =
+                counting::resolve_assumptions(&read_assumptions(args), counter.mapping());
+            println!(
+                "c o n={:?} seed={:?} a={:?}",
+                n,
+                seed,
+                ccg::literal_names(&assumptions, counter.mapping())
+            );
+
+            let mut rng = rug::rand::RandState::new();
+            rng.seed(&rug::Integer::from(seed));
+            let samples = counter.sample(assumptions.iter(), n, weights.as_ref(), &mut rng);
+            match samples.is_empty() && n > 0 {
+                true => println!("s UNSATISFIABLE"),
+                _ => {
+                    println!("s SATISFIABLE");
+                    for atoms in samples {
+                        println!("v {}", atoms.join(" "));
+                    }
+                }
+            }
+        }
+        Some("-models") => {
+            let n = args.next().as_deref().map(usize::from_str);
+            let mut args = args.peekable();
+            let ccg = args
+                .next()
+                .and_then(|s| if s == "-ccg" { args.next() } else { None });
+            let (n, ccg) = match (n, ccg) {
+                (Some(Ok(n)), Some(ccg)) => (n, ccg),
+                _ => {
+                    println!(
+                        "error: please provide input in the following order {:?}.",
+                        "N -ccg counting_graph [-aux]"
+                    );
+                    std::process::exit(-1)
+                }
+            };
+            let auxiliary = args.peek().map(|s| s.as_str()) == Some("-aux");
+            if auxiliary {
+                args.next();
+            }
+
+            let counter = counter::Counter::new(ccg).unwrap_or_else(|err| {
+                println!("error: {:?}.", err);
+                std::process::exit(-1)
+            });
+            let assumptions =
+                counting::resolve_assumptions(&read_assumptions(args), counter.mapping());
+            println!(
+                "c o n={:?} a={:?}",
+                n,
+                ccg::literal_names(&assumptions, counter.mapping())
+            );
+
+            let mut models = counter.models(assumptions.iter(), auxiliary).peekable();
+            match models.peek().is_none() {
+                true => println!("s UNSATISFIABLE"),
+                _ => {
+                    println!("s SATISFIABLE");
+                    // 0 lists all models
+                    for atoms in models.take(if n == 0 { usize::MAX } else { n }) {
+                        println!("v {}", atoms.join(" "));
+                    }
+                }
+            }
+        }
+        Some("-page") => {
+            let offset = args.next().map(|s| rug::Integer::from_str(&s));
+            let len = args.next().as_deref().map(usize::from_str);
+            let mut args = args.peekable();
+            let ccg = args
+                .next()
+                .and_then(|s| if s == "-ccg" { args.next() } else { None });
+            let (offset, len, ccg) = match (offset, len, ccg) {
+                (Some(Ok(offset)), Some(Ok(len)), Some(ccg)) => (offset, len, ccg),
+                _ => {
+                    println!(
+                        "error: please provide input in the following order {:?}.",
+                        "offset len -ccg counting_graph [-aux]"
+                    );
+                    std::process::exit(-1)
+                }
+            };
+            let auxiliary = args.peek().map(|s| s.as_str()) == Some("-aux");
+            if auxiliary {
+                args.next();
+            }
+
+            let counter = counter::Counter::new(ccg).unwrap_or_else(|err| {
+                println!("error: {:?}.", err);
+                std::process::exit(-1)
+            });
+            let assumptions =
+                counting::resolve_assumptions(&read_assumptions(args), counter.mapping());
+            println!(
+                "c o offset={} len={:?} a={:?}",
+                offset,
+                len,
+                ccg::literal_names(&assumptions, counter.mapping())
+            );
+
+            let count = counter.count(assumptions.iter());
+            match count > 0 {
+                true => {
+                    println!("s SATISFIABLE");
+                    println!("c s exact arb int {:?}", count);
+                    for atoms in counter
+                        .models_from(&offset, assumptions.iter(), auxiliary)
+                        .take(len)
+                    {
+                        println!("v {}", atoms.join(" "));
+                    }
+                }
+                _ => println!("s UNSATISFIABLE"),
+            }
+        }
+        Some("-condition") => {
+            let ccg = args
+                .next()
+                .and_then(|s| if s == "-ccg" { args.next() } else { None })
+                .unwrap_or_else(|| {
+                    println!("error: provide ccg file path with {:?}.", "-ccg path");
+                    std::process::exit(-1)
+                });
+            let assumptions = read_assumptions(args);
+
+            // conditioned graph is written in the format of the input
+            let stdout = std::io::stdout();
+            let mut handle = stdout.lock();
+            ccg::Ccg::is_binary(&ccg)
+                .and_then(|binary| {
+                    let graph = ccg::Ccg::read(&ccg)?;
+                    let assumptions = counting::resolve_assumptions(&assumptions, graph.mapping());
+                    let graph = graph.condition(&assumptions);
+                    match binary {
+                        true => graph.write_binary(&mut handle),
+                        _ => graph.write_text(&mut handle),
+                    }
+                })
+                .unwrap_or_else(|err| {
+                    println!("error: {:?}.", err.to_string());
+                    std::process::exit(-1)
+                })
+        }
+        Some("-minimize") => {
+            let ccg = args
+                .next()
+                .and_then(|s| if s.trim() == "-in" { args.next() } else { None })
+                .unwrap_or_else(|| {
+                    println!("error: provide ccg file path with {:?}.", "-in path");
+                    std::process::exit(-1)
+                });
+            let out = match args.next().as_deref() {
+                Some("-out") => args.next(),
+                _ => None,
+            };
+
+            let (graph, minimized, binary) = ccg::Ccg::is_binary(&ccg)
+                .and_then(|binary| {
+                    let graph = ccg::Ccg::read(&ccg)?;
+                    let minimized = graph.minimize();
+                    Ok((graph, minimized, binary))
+                })
+                .unwrap_or_else(|err| {
+                    println!("error: {:?}.", err.to_string());
+                    std::process::exit(-1)
+                });
+            let stats = [
+                format!(
+                    "c s nodes {:?} -> {:?}",
+                    graph.node_count(),
+                    minimized.node_count()
+                ),
+                format!(
+                    "c s edges {:?} -> {:?}",
+                    graph.edge_count(),
+                    minimized.edge_count()
+                ),
+            ];
+
+            // minimized graph is written in the format of the input, stats go
+            // to stderr unless the graph is written to a file
+            let written = match &out {
+                Some(path) => std::fs::File::create(path)
+                    .map_err(ccg::CcgError::from)
+                    .and_then(|mut file| match binary {
+                        true => minimized.write_binary(&mut file),
+                        _ => minimized.write_text(&mut file),
+                    }),
+                None => {
+                    let mut handle = std::io::stdout().lock();
+                    match binary {
+                        true => minimized.write_binary(&mut handle),
+                        _ => minimized.write_text(&mut handle),
+                    }
+                }
+            };
+            written.unwrap_or_else(|err| {
+                println!("error: {:?}.", err.to_string());
+                std::process::exit(-1)
+            });
+            for line in stats {
+                match out {
+                    Some(_) => println!("{}", line),
+                    _ => eprintln!("{}", line),
+                }
+            }
+        }
+        Some("-query") => {
+            let query = args.next();
+            let ccg = args
+                .next()
+                .and_then(|s| if s == "-ccg" { args.next() } else { None });
+            let (query, ccg) = match (query, ccg) {
+                (Some(query), Some(ccg)) => (query, ccg),
+                _ => {
+                    println!(
+                        "error: please provide input in the following order {:?}.",
+                        "formula -ccg counting_graph"
+                    );
+                    std::process::exit(-1)
+                }
+            };
+
+            let counter = counter::Counter::new(ccg).unwrap_or_else(|err| {
+                println!("error: {:?}.", err);
+                std::process::exit(-1)
+            });
+            let assumptions =
+                counting::resolve_assumptions(&read_assumptions(args), counter.mapping());
+            println!(
+                "c o q={:?} a={:?}",
+                query,
+                ccg::literal_names(&assumptions, counter.mapping())
+            );
+
+            let count = counter
+                .count_query(&query, assumptions.iter())
+                .unwrap_or_else(|err| {
+                    println!("error: {:?}.", err);
+                    std::process::exit(-1)
+                });
+            match count > 0 {
+                true => {
+                    println!("s SATISFIABLE");
+                    println!("c s log10-estimate {:?}", count.to_f64().log10());
+                    println!("c s exact arb int {:?}", count);
+                }
+                _ => println!("s UNSATISFIABLE"),
+            }
+        }
+        Some(mode @ ("-brave" | "-cautious" | "-facets")) => {
+            let mode = mode.to_owned();
+            let ccg = args
+                .next()
+                .and_then(|s| if s == "-ccg" { args.next() } else { None })
+                .unwrap_or_else(|| {
+                    println!("error: provide ccg file path with {:?}.", "-ccg path");
+                    std::process::exit(-1)
+                });
+
+            let counter = counter::Counter::new(ccg).unwrap_or_else(|err| {
+                println!("error: {:?}.", err);
+                std::process::exit(-1)
+            });
+            let assumptions =
+                counting::resolve_assumptions(&read_assumptions(args), counter.mapping());
+            println!(
+                "c o a={:?}",
+                ccg::literal_names(&assumptions, counter.mapping())
+            );
+
+            match counter.consequences(assumptions.iter()) {
+                Some(consequences) => {
+                    println!("s SATISFIABLE");
+                    let (kind, atoms) = match mode.as_str() {
+                        "-brave" => ("brave", consequences.brave),
+                        "-cautious" => ("cautious", consequences.cautious),
+                        _ => ("facets", consequences.facets),
+                    };
+                    println!("c s {} {:?}", kind, atoms.len());
+                    for (signature, atoms) in counter::by_signature(&atoms) {
+                        println!("c s {} {}", signature, atoms.join(" "));
+                    }
+                }
+                _ => println!("s UNSATISFIABLE"),
+            }
+        }
+        Some("-explain") => {
+            let mut args = args.peekable();
+            let ccg = args
+                .next()
+                .and_then(|s| if s == "-ccg" { args.next() } else { None })
+                .unwrap_or_else(|| {
+                    println!(
+                        "error: please provide input in the following order {:?}.",
+                        "-ccg counting_graph [-all]"
+                    );
+                    std::process::exit(-1)
+                });
+            let all = args.peek().map(|s| s.as_str()) == Some("-all");
+            if all {
+                args.next();
+            }
+
+            let counter = counter::Counter::new(ccg).unwrap_or_else(|err| {
+                println!("error: {:?}.", err);
+                std::process::exit(-1)
+            });
+            let assumptions =
+                counting::resolve_assumptions(&read_assumptions(args), counter.mapping());
+            println!(
+                "c o a={:?}",
+                ccg::literal_names(&assumptions, counter.mapping())
+            );
+
+            let explanations = match all {
+                true => counter.explain_all(assumptions.iter()),
+                _ => counter.explain(assumptions.iter()).into_iter().collect(),
+            };
+            match explanations.is_empty() {
+                true => println!("s SATISFIABLE"),
+                _ => {
+                    println!("s UNSATISFIABLE");
+                    for mus in explanations {
+                        println!("c s mus {}", mus.join(" "));
+                    }
+                }
+            }
+        }
+        Some("-relations") => {
+            let ccg = args
+                .next()
+                .and_then(|s| if s == "-ccg" { args.next() } else { None })
+                .unwrap_or_else(|| {
+                    println!("error: provide ccg file path with {:?}.", "-ccg path");
+                    std::process::exit(-1)
+                });
+
+            let counter = counter::Counter::new(ccg).unwrap_or_else(|err| {
+                println!("error: {:?}.", err);
+                std::process::exit(-1)
+            });
+            let assumptions =
+                counting::resolve_assumptions(&read_assumptions(args), counter.mapping());
+            println!(
+                "c o a={:?}",
+                ccg::literal_names(&assumptions, counter.mapping())
+            );
+
+            match counter.relations(assumptions.iter()) {
+                Some(relations) => {
+                    println!("s SATISFIABLE");
+                    for class in relations.equivalent {
+                        println!("c s equivalent {}", class.join(" "));
+                    }
+                    for (a, b) in relations.implications {
+                        println!("c s implies {} {}", a, b);
+                    }
+                    for (a, b) in relations.exclusive {
+                        println!("c s excludes {} {}", a, b);
+                    }
+                }
+                _ => println!("s UNSATISFIABLE"),
+            }
+        }
+        Some("-cooccurrence") => {
+            let mut args = args.peekable();
+            let ccg = args
+                .next()
+                .and_then(|s| if s == "-ccg" { args.next() } else { None });
+            let selection = args.next().zip(args.next());
+            let (ccg, (selection, atoms)) = match (ccg, selection) {
+                (Some(ccg), Some(selection))
+                    if selection.0 == "-atoms" || selection.0 == "-sig" =>
+                {
+                    (ccg, selection)
+                }
+                _ => {
+                    println!(
+                        "error: please provide input in the following order {:?}.",
+                        "-ccg counting_graph (-atoms \"a1 ... an\" | -sig name/arity) [-out csv_path]"
+                    );
+                    std::process::exit(-1)
+                }
+            };
+            let out = match args.peek().map(|s| s.as_str()) {
+                Some("-out") => {
+                    args.next();
+                    args.next()
+                }
+                _ => None,
+            };
+
+            let counter = counter::Counter::new(ccg).unwrap_or_else(|err| {
+                println!("error: {:?}.", err);
+                std::process::exit(-1)
+            });
+            let atoms = match selection.as_str() {
+                "-sig" => counter.atoms_of(&atoms),
+                _ => atoms.split_whitespace().map(|a| a.to_owned()).collect(),
+            };
+            let assumptions =
+                counting::resolve_assumptions(&read_assumptions(args), counter.mapping());
+
+            let matrix = counter
+                .cooccurrence(&atoms, assumptions.iter())
+                .unwrap_or_else(|err| {
+                    println!("error: {:?}.", err);
+                    std::process::exit(-1)
+                });
+            // csv goes to stdout unless written to a file
+            let written = match &out {
+                Some(path) => std::fs::File::create(path).and_then(|mut file| {
+                    println!(
+                        "c o n={:?} a={:?}",
+                        atoms.len(),
+                        ccg::literal_names(&assumptions, counter.mapping())
+                    );
+                    matrix.write_csv(&mut file)
+                }),
+                None => matrix.write_csv(&mut std::io::stdout().lock()),
+            };
+            written.unwrap_or_else(|err| {
+                println!("error: {:?}.", err.to_string());
+                std::process::exit(-1)
+            });
+            if out.is_some() {
+                println!("c s exact arb int {:?}", matrix.count);
+            }
+        }
+        Some("-suggest") => {
+            let mut args = args.peekable();
+            let ccg = args
+                .next()
+                .and_then(|s| if s == "-ccg" { args.next() } else { None })
+                .unwrap_or_else(|| {
+                    println!(
+                        "error: please provide input in the following order {:?}.",
+                        "-ccg counting_graph [-plan]"
+                    );
+                    std::process::exit(-1)
+                });
+            let plan = args.peek().map(|s| s.as_str()) == Some("-plan");
+            if plan {
+                args.next();
+            }
+
+            let counter = counter::Counter::new(ccg).unwrap_or_else(|err| {
+                println!("error: {:?}.", err);
+                std::process::exit(-1)
+            });
+            let assumptions =
+                counting::resolve_assumptions(&read_assumptions(args), counter.mapping());
+            println!(
+                "c o a={:?}",
+                ccg::literal_names(&assumptions, counter.mapping())
+            );
+
+            let count = counter.count(assumptions.iter());
+            match count > 0 {
+                true => {
+                    println!("s SATISFIABLE");
+                    println!("c s exact arb int {:?}", count);
+                    match plan {
+                        true => {
+                            for (i, (literal, count)) in
+                                counter.plan(assumptions.iter()).iter().enumerate()
+                            {
+                                println!("c s step {:?} {} {:?}", i + 1, literal, count);
+                            }
+                        }
+                        _ => {
+                            if let Some((atom, count, gain)) = counter.suggest(assumptions.iter())
+                            {
+                                println!("c s suggest {} {:?} {:.3}", atom, count, gain);
+                            }
+                        }
+                    }
+                }
+                _ => println!("s UNSATISFIABLE"),
+            }
+        }
+        Some("-batch") => {
+            let mut args = args.peekable();
+            let ccg = args
+                .next()
+                .and_then(|s| if s == "-ccg" { args.next() } else { None })
+                .unwrap_or_else(|| {
+                    println!(
+                        "error: please provide input in the following order {:?}.",
+                        "-ccg counting_graph [-in queries]"
+                    );
+                    std::process::exit(-1)
+                });
+            // one assumption set per line, read from stdin unless given
+            let queries = match args.peek().map(|s| s.as_str()) {
+                Some("-in") => {
+                    args.next();
+                    args.next().and_then(|f| read_to_string(f).ok())
+                }
+                _ => std::io::read_to_string(std::io::stdin()).ok(),
+            }
+            .unwrap_or_else(|| {
+                println!("error: reading queries failed.");
+                std::process::exit(-1)
+            });
+
+            let counter = counter::Counter::new(ccg).unwrap_or_else(|err| {
+                println!("error: {:?}.", err);
+                std::process::exit(-1)
+            });
+            let batch = queries
+                .lines()
+                .map(|l| {
+                    l.split_whitespace()
+                        .map(|a| a.to_owned())
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            for (i, query) in batch.iter().enumerate() {
+                if let Some(a) = query.iter().find(|a| counter.resolve(a).is_none()) {
+                    println!("error: unknown literal {:?} in query {:?}.", a, i + 1);
+                    std::process::exit(-1)
+                }
+            }
+            println!("c o n={:?}", batch.len());
+
+            for (i, count) in counter.count_batch(&batch).iter().enumerate() {
+                println!("c s {} {:?}", i + 1, count);
+            }
+        }
+        Some("repl" | "-repl") => {
+            let ccg = args
+                .next()
+                .and_then(|s| if s == "-ccg" { args.next() } else { None })
+                .unwrap_or_else(|| {
+                    println!("error: provide ccg file path with {:?}.", "-ccg path");
+                    std::process::exit(-1)
+                });
+            repl::run(&ccg);
+        }
+        Some("-server") => {
+            let usage = || {
+                println!(
+                    "error: please provide input in the following order {:?}.",
+                    "-ccg [name=]counting_graph ... [-socket path | -tcp address]"
+                );
+                std::process::exit(-1)
+            };
+            let mut graphs = vec![];
+            let mut transport = server::Transport::Stdio;
+            while let Some(arg) = args.next() {
+                match (arg.as_str(), args.next()) {
+                    ("-ccg", Some(graph)) => graphs.push(match graph.split_once('=') {
+                        Some((name, path)) => (name.to_owned(), path.to_owned()),
+                        _ => (graph.clone(), graph),
+                    }),
+                    ("-socket", Some(path)) => transport = server::Transport::Unix(path),
+                    ("-tcp", Some(address)) => transport = server::Transport::Tcp(address),
+                    _ => usage(),
+                }
+            }
+            if graphs.is_empty() {
+                usage()
+            }
+
+            let server = server::Server::new(&graphs).unwrap_or_else(|err| {
+                println!("error: {:?}.", err);
+                std::process::exit(-1)
+            });
+            server.run(transport).unwrap_or_else(|err| {
+                println!("error: {:?}.", err.to_string());
+                std::process::exit(-1)
+            });
+        }
         Some("-nnf") => args
             .next()
             .and_then(|s| if s.trim() == "-in" { args.next() } else { None })
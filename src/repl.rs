@@ -0,0 +1,433 @@
+use rug::rand::RandState;
+use rug::Integer;
+use std::io::{BufRead, Read, Write};
+use std::str::FromStr;
+
+use crate::ccg::literal_names;
+use crate::counter::{by_signature, Counter, MAX_SAMPLES};
+
+const COMMANDS: [&str; 10] = [
+    "assume",
+    "count",
+    "exit",
+    "explain",
+    "facets",
+    "help",
+    "marginals",
+    "quit",
+    "retract",
+    "sample",
+];
+
+const HELP: &str = "\
+c count                 count answer sets under the assumptions
+c assume l1 ... ln      add assumptions given as a or ~a
+c retract [l1 ... ln]   retract assumptions, or all of them
+c facets                list facets under the assumptions
+c marginals             count answer sets under each literal
+c sample [n] [seed]     draw n answer sets uniformly at random
+c explain               minimal subset of the assumptions without answer sets
+c quit                  leave";
+
+/// Reads the counting graph `ccg` once and answers commands read from stdin
+/// under assumptions kept between commands.
+pub fn run(ccg: &str) {
+    let counter = Counter::new(ccg).unwrap_or_else(|err| {
+        println!("error: {:?}.", err);
+        std::process::exit(-1)
+    });
+    let mut evaluator = counter.incremental();
+    let mut rng = RandState::new();
+    rng.seed(&Integer::from(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0),
+    ));
+
+    let mut atoms = counter.atoms().map(|a| a.to_owned()).collect::<Vec<_>>();
+    atoms.sort();
+    let mut editor = LineEditor::new(atoms);
+
+    while let Some(line) = editor.read_line("iascar> ") {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            None => continue,
+            Some("count") => {
+                let count = evaluator.count();
+                println!(
+                    "c o a={:?}",
+                    literal_names(evaluator.assumptions(), counter.mapping())
+                );
+                match count > 0 {
+                    true => {
+                        println!("s SATISFIABLE");
+                        println!("c s log10-estimate {:?}", count.to_f64().log10());
+                        println!("c s exact arb int {:?}", count);
+                    }
+                    _ => println!("s UNSATISFIABLE"),
+                }
+            }
+            Some("assume") => {
+                for literal in words {
+                    if !evaluator.push_assumption(literal) {
+                        println!("error: unknown literal {:?}.", literal);
+                    }
+                }
+                println!(
+                    "c o a={:?}",
+                    literal_names(evaluator.assumptions(), counter.mapping())
+                );
+            }
+            Some("retract") => {
+                let mut words = words.peekable();
+                if words.peek().is_none() {
+                    while evaluator.pop_assumption().is_some() {}
+                }
+                for literal in words {
+                    if !evaluator.retract_assumption(literal) {
+                        println!("error: {:?} is not assumed.", literal);
+                    }
+                }
+                println!(
+                    "c o a={:?}",
+                    literal_names(evaluator.assumptions(), counter.mapping())
+                );
+            }
+            Some("facets") => match counter.consequences(evaluator.assumptions().iter()) {
+                Some(consequences) => {
+                    println!("s SATISFIABLE");
+                    println!("c s facets {:?}", consequences.facets.len());
+                    for (signature, atoms) in by_signature(&consequences.facets) {
+                        println!("c s {} {}", signature, atoms.join(" "));
+                    }
+                }
+                _ => println!("s UNSATISFIABLE"),
+            },
+            Some("marginals") => {
+                let count = evaluator.count();
+                match count > 0 {
+                    true => {
+                        println!("s SATISFIABLE");
+                        println!("c s exact arb int {:?}", count);
+                        let marginals = counter.marginals(evaluator.assumptions().iter());
+                        for atom in editor.atoms() {
+                            for literal in [atom.clone(), format!("~{atom}")] {
+                                if let Some(count) = marginals.get(&literal) {
+                                    println!("c s {} {:?}", literal, count);
+                                }
+                            }
+                        }
+                    }
+                    _ => println!("s UNSATISFIABLE"),
+                }
+            }
+            Some("sample") => {
+                let n = words.next().map(usize::from_str).unwrap_or(Ok(1));
+                let seed = words.next().map(u64::from_str).transpose();
+                match (n, seed) {
+                    (Ok(n), _) if n > MAX_SAMPLES => {
+                        println!("error: sample size must be at most {}.", MAX_SAMPLES)
+                    }
+                    (Ok(n), Ok(seed)) => {
+                        if let Some(seed) = seed {
+                            rng.seed(&Integer::from(seed));
+                        }
+                        let samples =
+                            counter.sample(evaluator.assumptions().iter(), n, None, &mut rng);
+                        match samples.is_empty() && n > 0 {
+                            true => println!("s UNSATISFIABLE"),
+                            _ => {
+                                println!("s SATISFIABLE");
+                                for atoms in samples {
+                                    println!("v {}", atoms.join(" "));
+                                }
+                            }
+                        }
+                    }
+                    _ => println!(
+                        "error: provide sample size and seed as {:?}.",
+                        "sample [n] [seed]"
+                    ),
+                }
+            }
+            Some("explain") => match counter.explain(evaluator.assumptions().iter()) {
+                Some(mus) => {
+                    println!("s UNSATISFIABLE");
+                    println!("c s mus {}", mus.join(" "));
+                }
+                _ => println!("s SATISFIABLE"),
+            },
+            Some("help") => println!("{}", HELP),
+            Some("quit" | "exit") => break,
+            Some(command) => println!("error: unknown command {:?}, try {:?}.", command, "help"),
+        }
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Minimal line editor with history and completion of commands and atoms.
+///
+/// Falls back to plain reading of lines if stdin is not a terminal, e.g., if
+/// commands are piped.
+struct LineEditor {
+    atoms: Vec<String>,
+    history: Vec<String>,
+    interactive: bool,
+}
+
+impl LineEditor {
+    fn new(atoms: Vec<String>) -> Self {
+        Self {
+            atoms,
+            history: vec![],
+            interactive: unsafe { libc::isatty(libc::STDIN_FILENO) == 1 },
+        }
+    }
+
+    /// Returns the atoms that are completed, ordered by name.
+    fn atoms(&self) -> &[String] {
+        &self.atoms
+    }
+
+    /// Reads the next line, or returns `None` at end of input.
+    fn read_line(&mut self, prompt: &str) -> Option<String> {
+        let line = match self.interactive {
+            true => match RawMode::enable() {
+                Some(_raw) => self.edit(prompt)?,
+                _ => {
+                    print!("{prompt}");
+                    let _ = std::io::stdout().flush();
+                    read_plain()?
+                }
+            },
+            _ => read_plain()?,
+        };
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+
+        Some(line)
+    }
+
+    /// Edits a line in raw mode: tab completes, up and down browse the
+    /// history, backspace deletes, ctrl-u clears, ctrl-c discards the line
+    /// and ctrl-d on an empty line ends input.
+    fn edit(&self, prompt: &str) -> Option<String> {
+        let mut stdout = std::io::stdout();
+        let mut line: Vec<u8> = vec![];
+        let mut entry = self.history.len();
+        redraw(&mut stdout, prompt, &line);
+
+        loop {
+            match read_byte()? {
+                b'\r' | b'\n' => {
+                    println!();
+                    return Some(String::from_utf8_lossy(&line).into_owned());
+                }
+                3 => {
+                    println!("^C");
+                    line.clear();
+                }
+                4 if line.is_empty() => {
+                    println!();
+                    return None;
+                }
+                8 | 127 => {
+                    // drop a whole utf-8 character
+                    while let Some(byte) = line.pop() {
+                        if byte & 0xc0 != 0x80 {
+                            break;
+                        }
+                    }
+                }
+                21 => line.clear(),
+                b'\t' => {
+                    let candidates = self.complete(&mut line);
+                    if candidates.len() > 1 {
+                        println!();
+                        println!("{}", candidates.join(" "));
+                    }
+                }
+                27 => {
+                    if read_byte()? != b'[' {
+                        continue;
+                    }
+                    match read_byte()? {
+                        b'A' if entry > 0 => entry -= 1,
+                        b'B' if entry < self.history.len() => entry += 1,
+                        _ => continue,
+                    }
+                    line = self
+                        .history
+                        .get(entry)
+                        .map(|l| l.as_bytes().to_vec())
+                        .unwrap_or_default();
+                }
+                byte if byte >= 32 => line.push(byte),
+                _ => continue,
+            }
+            redraw(&mut stdout, prompt, &line);
+        }
+    }
+
+    /// Extends the last word of `line` by the common prefix of its
+    /// completions, which are commands for the first word and atoms, possibly
+    /// negated, otherwise. Returns the completions.
+    fn complete(&self, line: &mut Vec<u8>) -> Vec<String> {
+        let text = String::from_utf8_lossy(line).into_owned();
+        let start = text.rfind(' ').map_or(0, |i| i + 1);
+        let (negation, word) = match text[start..].strip_prefix('~') {
+            Some(word) if start > 0 => ("~", word),
+            _ => ("", &text[start..]),
+        };
+        let candidates = match start {
+            0 => COMMANDS
+                .iter()
+                .filter(|c| c.starts_with(word))
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>(),
+            _ => self
+                .atoms
+                .iter()
+                .filter(|a| a.starts_with(word))
+                .cloned()
+                .collect(),
+        };
+
+        let prefix = candidates.iter().skip(1).fold(
+            candidates.first().map_or("", |c| c.as_str()),
+            |prefix, c| {
+                let len = prefix
+                    .char_indices()
+                    .zip(c.chars())
+                    .take_while(|((_, x), y)| x == y)
+                    .last()
+                    .map_or(0, |((i, x), _)| i + x.len_utf8());
+                &prefix[..len]
+            },
+        );
+        if prefix.len() > word.len() || candidates.len() == 1 {
+            line.truncate(start);
+            line.extend_from_slice(negation.as_bytes());
+            line.extend_from_slice(prefix.as_bytes());
+            if candidates.len() == 1 {
+                line.push(b' ');
+            }
+        }
+
+        candidates
+    }
+}
+
+/// Terminal without line buffering, echo and signals, restored when dropped.
+///
+/// Ctrl-c is read as byte 3 instead of interrupting, such that the terminal
+/// is always restored.
+struct RawMode(libc::termios);
+
+impl RawMode {
+    fn enable() -> Option<Self> {
+        unsafe {
+            let mut termios = std::mem::zeroed::<libc::termios>();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                return None;
+            }
+            let original = termios;
+            termios.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+            termios.c_cc[libc::VMIN] = 1;
+            termios.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) != 0 {
+                return None;
+            }
+            Some(Self(original))
+        }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.0);
+        }
+    }
+}
+
+fn read_plain() -> Option<String> {
+    let mut line = String::new();
+    match std::io::stdin().lock().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        _ => Some(line.trim_end().to_owned()),
+    }
+}
+
+fn read_byte() -> Option<u8> {
+    let mut byte = [0u8];
+    match std::io::stdin().lock().read(&mut byte) {
+        Ok(1) => Some(byte[0]),
+        _ => None,
+    }
+}
+
+fn redraw(stdout: &mut std::io::Stdout, prompt: &str, line: &[u8]) {
+    let _ = write!(stdout, "\r\x1b[K{}", prompt);
+    let _ = stdout.write_all(line);
+    let _ = stdout.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn complete(atoms: &[&str], line: &str) -> (String, Vec<String>) {
+        let editor = LineEditor::new(atoms.iter().map(|a| a.to_string()).collect());
+        let mut line = line.as_bytes().to_vec();
+        let candidates = editor.complete(&mut line);
+        (String::from_utf8(line).unwrap(), candidates)
+    }
+
+    #[test]
+    fn complete_commands_and_atoms() {
+        assert_eq!(
+            complete(&[], "co"),
+            ("count ".to_owned(), vec!["count".to_owned()])
+        );
+        assert_eq!(
+            complete(&[], "e"),
+            (
+                "ex".to_owned(),
+                vec!["exit".to_owned(), "explain".to_owned()]
+            )
+        );
+        // the first word is always a command
+        assert_eq!(complete(&["a"], "a").0, "assume ");
+
+        let atoms = ["a", "h", "p(1)", "p(2)"];
+        assert_eq!(complete(&atoms, "assume a ~h").0, "assume a ~h ");
+        assert_eq!(
+            complete(&atoms, "retract ~"),
+            ("retract ~".to_owned(), atoms.map(|a| a.to_owned()).to_vec())
+        );
+        assert_eq!(
+            complete(&atoms, "assume p"),
+            (
+                "assume p(".to_owned(),
+                vec!["p(1)".to_owned(), "p(2)".to_owned()]
+            )
+        );
+    }
+
+    #[test]
+    fn complete_multibyte_atoms() {
+        let atoms = ["größe", "größer", "äpfel", "äste"];
+        assert_eq!(complete(&atoms, "assume g").0, "assume größe");
+        assert_eq!(
+            complete(&atoms, "assume ~ä"),
+            (
+                "assume ~ä".to_owned(),
+                vec!["äpfel".to_owned(), "äste".to_owned()]
+            )
+        );
+        assert_eq!(complete(&atoms, "assume ~äp").0, "assume ~äpfel ");
+    }
+}
@@ -0,0 +1,528 @@
+//! JSON-RPC 2.0 server answering counting queries on counting graphs that
+//! are read once.
+//!
+//! Requests and responses are JSON objects on a line each. Every request is
+//! evaluated as a job on the rayon pool, such that responses may arrive out
+//! of order, and is cancelled by the `cancel` method with its id. Counts are
+//! written as decimal strings, as they exceed the range of JSON numbers.
+
+use rug::rand::RandState;
+use rug::Integer;
+use serde_json::{json, Map, Value};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::counter::{by_signature, Counter, MAX_SAMPLES};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const REQUEST_CANCELLED: i64 = -32800;
+
+const METHODS: [&str; 5] = ["count", "marginals", "facets", "anytime_count", "sample"];
+
+/// Where requests are read from and responses written to.
+pub enum Transport {
+    Stdio,
+    Unix(String),
+    Tcp(String),
+}
+
+#[derive(Debug)]
+struct RpcError(i64, String);
+
+/// Ids of the requests of a connection that are being evaluated, along with
+/// their cancellation flags.
+type Pending = Mutex<HashMap<String, Arc<AtomicBool>>>;
+
+pub struct Server {
+    graphs: HashMap<String, Counter>,
+}
+
+impl Server {
+    /// Reads the counting graphs given as pairs of name and path.
+    pub fn new(graphs: &[(String, String)]) -> crate::counter::Result<Self> {
+        Ok(Self {
+            graphs: graphs
+                .iter()
+                .map(|(name, path)| Ok((name.clone(), Counter::new(path)?)))
+                .collect::<crate::counter::Result<_>>()?,
+        })
+    }
+
+    /// Serves `transport` until stdin ends, or forever for sockets, where
+    /// every connection is served on a thread of its own.
+    pub fn run(self, transport: Transport) -> std::io::Result<()> {
+        let server = Arc::new(self);
+        match transport {
+            Transport::Stdio => {
+                server.serve(std::io::stdin().lock(), std::io::stdout());
+                Ok(())
+            }
+            #[cfg(unix)]
+            Transport::Unix(path) => {
+                use std::os::unix::fs::FileTypeExt;
+
+                // only a stale socket of an earlier run is replaced
+                match std::fs::symlink_metadata(&path) {
+                    Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(&path)?,
+                    Ok(_) => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::AddrInUse,
+                            format!("address in use: {path}"),
+                        ))
+                    }
+                    _ => (),
+                }
+                let listener = std::os::unix::net::UnixListener::bind(&path)?;
+                for stream in listener.incoming() {
+                    let stream = stream?;
+                    let server = server.clone();
+                    let reader = BufReader::new(stream.try_clone()?);
+                    std::thread::spawn(move || server.serve(reader, stream));
+                }
+                Ok(())
+            }
+            #[cfg(not(unix))]
+            Transport::Unix(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "unix sockets are not supported on this platform",
+            )),
+            Transport::Tcp(address) => {
+                let listener = std::net::TcpListener::bind(&address)?;
+                for stream in listener.incoming() {
+                    let stream = stream?;
+                    let server = server.clone();
+                    let reader = BufReader::new(stream.try_clone()?);
+                    std::thread::spawn(move || server.serve(reader, stream));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Answers the requests read from `input` until it ends, waiting for the
+    /// evaluation of those still running.
+    fn serve(&self, input: impl BufRead, output: impl Write + Send) {
+        let output = Mutex::new(output);
+        let pending = Pending::default();
+        let reply = |response: Value| {
+            let mut output = output.lock().unwrap();
+            let _ = writeln!(output, "{}", response).and_then(|_| output.flush());
+        };
+
+        #[cfg(not(feature = "seq"))]
+        rayon::in_place_scope(|s| {
+            for line in input.lines().map_while(|l| l.ok()) {
+                if let Some(job) = self.dispatch(&line, &pending, &reply) {
+                    s.spawn(move |_| job());
+                }
+            }
+        });
+        #[cfg(feature = "seq")]
+        for line in input.lines().map_while(|l| l.ok()) {
+            if let Some(job) = self.dispatch(&line, &pending, &reply) {
+                job();
+            }
+        }
+    }
+
+    /// Reads a request, answering cancellations and malformed requests right
+    /// away, and returns the evaluation of any other request as job.
+    fn dispatch<'a>(
+        &'a self,
+        line: &str,
+        pending: &'a Pending,
+        reply: &'a (dyn Fn(Value) + Sync),
+    ) -> Option<Box<dyn FnOnce() + Send + 'a>> {
+        if line.trim().is_empty() {
+            return None;
+        }
+        let request = match serde_json::from_str::<Value>(line) {
+            Ok(request @ Value::Object(_)) => request,
+            Ok(_) => {
+                reply(response(Value::Null, Err(invalid_request())));
+                return None;
+            }
+            Err(err) => {
+                reply(response(
+                    Value::Null,
+                    Err(RpcError(PARSE_ERROR, err.to_string())),
+                ));
+                return None;
+            }
+        };
+        // requests without id are notifications that get no response
+        let id = request.get("id").cloned();
+
+        if request.get("method").and_then(Value::as_str) == Some("cancel") {
+            let cancelled = request
+                .get("params")
+                .and_then(|p| p.get("id"))
+                .and_then(|id| pending.lock().unwrap().get(&id.to_string()).cloned())
+                .map(|flag| flag.store(true, Ordering::Relaxed))
+                .is_some();
+            if let Some(id) = id {
+                reply(response(id, Ok(Value::Bool(cancelled))));
+            }
+            return None;
+        }
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        if let Some(id) = &id {
+            // ids identify requests to cancel, so they are unique among those pending
+            match pending.lock().unwrap().entry(id.to_string()) {
+                Entry::Occupied(_) => {
+                    reply(response(
+                        id.clone(),
+                        Err(RpcError(
+                            INVALID_REQUEST,
+                            "id of a pending request".to_owned(),
+                        )),
+                    ));
+                    return None;
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(cancelled.clone());
+                }
+            }
+        }
+        Some(Box::new(move || {
+            let outcome = self.handle(&request, &cancelled);
+            if let Some(id) = id {
+                pending.lock().unwrap().remove(&id.to_string());
+                reply(response(id, outcome));
+            }
+        }))
+    }
+
+    /// Evaluates `request`. Cancellation is noticed before and after the
+    /// evaluation, between its passes, between the alternations of anytime
+    /// counts and between samples.
+    fn handle(&self, request: &Value, cancelled: &AtomicBool) -> Result<Value, RpcError> {
+        let method = request
+            .get("method")
+            .and_then(Value::as_str)
+            .ok_or_else(invalid_request)?;
+        if !METHODS.contains(&method) {
+            return Err(RpcError(
+                METHOD_NOT_FOUND,
+                format!("unknown method {:?}", method),
+            ));
+        }
+        let params = request.get("params").unwrap_or(&Value::Null);
+        let counter = self.graph(params)?;
+        let assumptions = assumptions(counter, params)?;
+        let is_cancelled = || cancelled.load(Ordering::Relaxed);
+        let check = || match is_cancelled() {
+            true => Err(request_cancelled()),
+            _ => Ok(()),
+        };
+        check()?;
+
+        let result = match method {
+            "count" => json!({ "count": integer(&counter.count(assumptions.iter())) }),
+            "marginals" => {
+                let count = counter.count(assumptions.iter());
+                check()?;
+                let marginals = counter
+                    .marginals(assumptions.iter())
+                    .into_iter()
+                    .map(|(literal, count)| (literal, integer(&count)))
+                    .collect::<Map<_, _>>();
+                json!({ "count": integer(&count), "marginals": marginals })
+            }
+            "facets" => match counter.consequences(assumptions.iter()) {
+                Some(consequences) => json!({
+                    "satisfiable": true,
+                    "facets": by_signature(&consequences.facets)
+                        .into_iter()
+                        .map(|(signature, atoms)| (signature, json!(atoms)))
+                        .collect::<Map<_, _>>(),
+                }),
+                _ => json!({ "satisfiable": false }),
+            },
+            "anytime_count" => {
+                let constraints = params
+                    .get("constraints")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| invalid_params("constraints must be a string"))?;
+                let depth = optional_u64(params, "depth")?.unwrap_or(0) as usize;
+                let mut bounds = vec![];
+                let count = counter
+                    .count_denying_anytime(constraints, assumptions.iter(), depth, |_, bound| {
+                        bounds.push(integer(bound));
+                        !is_cancelled()
+                    })
+                    .map_err(|err| invalid_params(&format!("{:?}", err)))?;
+                json!({ "count": integer(&count), "bounds": bounds })
+            }
+            _ => {
+                let n = optional_u64(params, "n")?.unwrap_or(1);
+                if n > MAX_SAMPLES as u64 {
+                    return Err(invalid_params(&format!(
+                        "n must be at most {}",
+                        MAX_SAMPLES
+                    )));
+                }
+                let seed = match optional_u64(params, "seed")? {
+                    Some(seed) => seed,
+                    _ => std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_nanos() as u64)
+                        .unwrap_or(0),
+                };
+                let mut rng = RandState::new();
+                rng.seed(&Integer::from(seed));
+                let samples = counter.sample_until(
+                    assumptions.iter(),
+                    n as usize,
+                    None,
+                    &mut rng,
+                    is_cancelled,
+                );
+                json!({ "seed": seed, "samples": samples })
+            }
+        };
+
+        check().map(|_| result)
+    }
+
+    /// Returns the graph named by parameter `ccg`, which may be left out if
+    /// only one graph is served.
+    fn graph(&self, params: &Value) -> Result<&Counter, RpcError> {
+        match params.get("ccg") {
+            Some(name) => name
+                .as_str()
+                .and_then(|name| self.graphs.get(name))
+                .ok_or_else(|| invalid_params(&format!("unknown ccg {}", name))),
+            None if self.graphs.len() == 1 => Ok(self.graphs.values().next().unwrap()),
+            None => Err(invalid_params("ccg must name a graph")),
+        }
+    }
+}
+
+/// Reads parameter `assumptions`, an array of literals `a`, `~a` or cnf
+/// literals, rejecting unknown atoms.
+fn assumptions(counter: &Counter, params: &Value) -> Result<Vec<String>, RpcError> {
+    let assumptions = match params.get("assumptions") {
+        Some(assumptions) => assumptions
+            .as_array()
+            .ok_or_else(|| invalid_params("assumptions must be an array"))?,
+        None => &vec![],
+    };
+    assumptions
+        .iter()
+        .map(|a| {
+            let literal = match a {
+                Value::String(s) => s.clone(),
+                Value::Number(n) => n.to_string(),
+                _ => return Err(invalid_params("assumptions must be strings or integers")),
+            };
+            match counter.resolve(&literal) {
+                Some(_) => Ok(literal),
+                _ => Err(invalid_params(&format!("unknown literal {:?}", literal))),
+            }
+        })
+        .collect()
+}
+
+fn optional_u64(params: &Value, key: &str) -> Result<Option<u64>, RpcError> {
+    params
+        .get(key)
+        .map(|value| {
+            value
+                .as_u64()
+                .ok_or_else(|| invalid_params(&format!("{} must be a non-negative integer", key)))
+        })
+        .transpose()
+}
+
+fn integer(n: &Integer) -> Value {
+    Value::String(n.to_string())
+}
+
+fn response(id: Value, outcome: Result<Value, RpcError>) -> Value {
+    match outcome {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(RpcError(code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message },
+        }),
+    }
+}
+
+fn invalid_request() -> RpcError {
+    RpcError(INVALID_REQUEST, "invalid request".to_owned())
+}
+
+fn invalid_params(message: &str) -> RpcError {
+    RpcError(INVALID_PARAMS, message.to_owned())
+}
+
+fn request_cancelled() -> RpcError {
+    RpcError(REQUEST_CANCELLED, "request cancelled".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answer_requests() {
+        let example = "examples/example.lp.as.cnf.nnf.ccg".to_owned();
+        let server = Server::new(&[("ex".to_owned(), example)]).unwrap();
+        let requests = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"count","params":{"ccg":"ex","assumptions":["~i","b"]}}"#,
+            r#"{"jsonrpc":"2.0","id":"m","method":"marginals","params":{"assumptions":["a"]}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"facets","params":{"assumptions":["a","b"]}}"#,
+            r#"{"jsonrpc":"2.0","id":4,"method":"anytime_count","params":{"constraints":":- h.","depth":1}}"#,
+            r#"{"jsonrpc":"2.0","id":5,"method":"sample","params":{"n":2,"seed":7,"assumptions":["a"]}}"#,
+            r#"{"jsonrpc":"2.0","id":6,"method":"count","params":{"assumptions":["x"]}}"#,
+            r#"{"jsonrpc":"2.0","id":7,"method":"solve"}"#,
+            r#"{"jsonrpc":"2.0","id":8,"method":"cancel","params":{"id":42}}"#,
+            r#"{"jsonrpc":"2.0","method":"count"}"#,
+            r#"{"jsonrpc":"2.0","id":9,"#,
+            r#"{"jsonrpc":"2.0","id":10,"method":"sample","params":{"n":1e12}}"#,
+            r#"{"jsonrpc":"2.0","id":11,"method":"sample","params":{"n":100001}}"#,
+        ];
+        let mut output = vec![];
+        server.serve(requests.join("\n").as_bytes(), &mut output);
+
+        let responses = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str::<Value>(l).unwrap())
+            .map(|r| (r.get("id").unwrap().to_string(), r))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(responses.len(), 11);
+        let result = |id: &str, key: &str| {
+            responses[id]
+                .get("result")
+                .and_then(|r| r.get(key))
+                .map(|v| v.to_string())
+        };
+        let error = |id: &str| {
+            responses[id]
+                .get("error")
+                .and_then(|e| e.get("code"))
+                .map(|v| v.to_string())
+        };
+        assert_eq!(result("1", "count").as_deref(), Some("\"2\""));
+        assert_eq!(result("\"m\"", "count").as_deref(), Some("\"2\""));
+        assert_eq!(
+            responses["\"m\""]
+                .get("result")
+                .and_then(|r| r.get("marginals"))
+                .and_then(|m| m.get("~h"))
+                .and_then(Value::as_str),
+            Some("1")
+        );
+        assert_eq!(result("3", "satisfiable").as_deref(), Some("false"));
+        assert_eq!(result("4", "count").as_deref(), Some("\"3\""));
+        assert_eq!(result("4", "bounds").as_deref(), Some("[\"6\",\"3\"]"));
+        assert_eq!(
+            result("5", "samples")
+                .as_deref()
+                .map(|s| s.matches('[').count()),
+            Some(3)
+        );
+        assert_eq!(error("6").as_deref(), Some("-32602"));
+        assert_eq!(error("7").as_deref(), Some("-32601"));
+        assert_eq!(responses["8"].get("result"), Some(&Value::Bool(false)));
+        assert_eq!(error("null").as_deref(), Some("-32700"));
+        assert_eq!(error("10").as_deref(), Some("-32602"));
+        assert_eq!(error("11").as_deref(), Some("-32602"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keep_files_at_socket_path() {
+        let path = std::env::temp_dir().join("iascar_keep_files_at_socket_path");
+        std::fs::write(&path, "keep").unwrap();
+        let server = Server::new(&[]).unwrap();
+        let err = server
+            .run(Transport::Unix(path.display().to_string()))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn cancel_pending_request() {
+        let server = Server::new(&[(
+            "ex".to_owned(),
+            "examples/example.lp.as.cnf.nnf.ccg".to_owned(),
+        )])
+        .unwrap();
+        let pending = Pending::default();
+        let responses = Mutex::new(vec![]);
+        let reply = |response: Value| responses.lock().unwrap().push(response);
+
+        let job = server.dispatch(r#"{"id":1,"method":"count"}"#, &pending, &reply);
+        assert!(server
+            .dispatch(
+                r#"{"id":2,"method":"cancel","params":{"id":1}}"#,
+                &pending,
+                &reply
+            )
+            .is_none());
+        job.unwrap()();
+
+        let responses = responses.into_inner().unwrap();
+        assert_eq!(responses[0].get("result"), Some(&Value::Bool(true)));
+        assert_eq!(
+            responses[1].get("error").and_then(|e| e.get("code")),
+            Some(&json!(REQUEST_CANCELLED))
+        );
+        assert!(pending.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reject_duplicate_pending_id() {
+        let server = Server::new(&[(
+            "ex".to_owned(),
+            "examples/example.lp.as.cnf.nnf.ccg".to_owned(),
+        )])
+        .unwrap();
+        let pending = Pending::default();
+        let responses = Mutex::new(vec![]);
+        let reply = |response: Value| responses.lock().unwrap().push(response);
+
+        let job = server.dispatch(r#"{"id":1,"method":"count"}"#, &pending, &reply);
+        assert!(server
+            .dispatch(r#"{"id":1,"method":"count"}"#, &pending, &reply)
+            .is_none());
+        assert!(server
+            .dispatch(
+                r#"{"id":2,"method":"cancel","params":{"id":1}}"#,
+                &pending,
+                &reply
+            )
+            .is_none());
+        job.unwrap()();
+        let job = server.dispatch(r#"{"id":1,"method":"count"}"#, &pending, &reply);
+        job.unwrap()();
+
+        let responses = responses.into_inner().unwrap();
+        assert_eq!(
+            responses[0].get("error").and_then(|e| e.get("code")),
+            Some(&json!(INVALID_REQUEST))
+        );
+        assert_eq!(responses[1].get("result"), Some(&Value::Bool(true)));
+        assert_eq!(
+            responses[2].get("error").and_then(|e| e.get("code")),
+            Some(&json!(REQUEST_CANCELLED))
+        );
+        assert_eq!(
+            responses[3].get("result").and_then(|r| r.get("count")),
+            Some(&json!("6"))
+        );
+    }
+}